@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::{pipeline::CollisionEvent, plugin::RapierConfiguration};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    aim,
+    player::{ChargeSettings, FireEvent, Gun, Player, Shot},
+    team::Team,
+    turn::{ActivePlayer, TurnStarted},
+    weapon::Weapon,
+};
+
+/// Seconds the computer waits before throwing, so its turn can be followed
+const THINK_TIME: f32 = 1.0;
+/// Degrees above the horizon the computer throws at
+const THROW_ANGLE: f32 = 45.0;
+/// Fraction the force of a throw is off by at random
+const SLOPPINESS: f32 = 0.05;
+
+/// A gorilla played by the computer. It lobs a random weapon at the closest opponent and
+/// corrects its force by where its previous shots of that weapon landed.
+#[derive(Component, Debug)]
+pub struct Ai {
+    think: Timer,
+    /// Learned correction of the force a perfect throw would need, per weapon since heavier
+    /// shots fly shorter
+    force_scale: HashMap<Weapon, f32>,
+    throw: Option<Throw>,
+}
+
+impl Default for Ai {
+    fn default() -> Self {
+        Self {
+            think: Timer::from_seconds(THINK_TIME, TimerMode::Once),
+            force_scale: HashMap::new(),
+            throw: None,
+        }
+    }
+}
+
+/// A shot the computer is following to learn from.
+#[derive(Debug)]
+struct Throw {
+    /// `None` until the shot has been spawned
+    shot: Option<Entity>,
+    weapon: Weapon,
+    from: Vec2,
+    target: Vec2,
+    /// Where the shot was last seen
+    last_seen: Vec2,
+}
+
+/// Force that lands a shot thrown at 45 degrees `distance` pixels away on flat ground,
+/// if force were speed.
+fn ideal_force(distance: f32, gravity: f32) -> f32 {
+    (distance.abs() * gravity.abs()).sqrt()
+}
+
+/// Force correction after a shot meant to go `wanted` pixels sideways went `landed` pixels.
+/// The distance of a throw grows with the square of its speed.
+fn corrected_scale(scale: f32, wanted: f32, landed: f32) -> f32 {
+    if landed.abs() < 1.0 || wanted.signum() != landed.signum() {
+        return scale;
+    }
+    scale * (wanted / landed).sqrt().clamp(0.5, 2.0)
+}
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (start_thinking, throw, follow_throw));
+    }
+}
+
+fn start_thinking(mut turn_started: EventReader<TurnStarted>, mut ai_query: Query<&mut Ai>) {
+    for event in turn_started.read() {
+        if let Ok(mut ai) = ai_query.get_mut(event.player) {
+            ai.think.reset();
+        }
+    }
+}
+
+fn throw(
+    time: Res<Time>,
+    charge: Res<ChargeSettings>,
+    rapier_config: Res<RapierConfiguration>,
+    mut fire: EventWriter<FireEvent>,
+    mut ai_query: Query<(&GlobalTransform, &Team, &mut Gun, &mut Ai), With<ActivePlayer>>,
+    opponent_query: Query<(&GlobalTransform, &Team), With<Player>>,
+) {
+    let Ok((transform, team, mut gun, mut ai)) = ai_query.get_single_mut() else {
+        return;
+    };
+    if !ai.think.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let position = transform.translation().truncate();
+    let Some(target) = opponent_query
+        .iter()
+        .filter(|(_, other)| *other != team)
+        .map(|(transform, _)| transform.translation().truncate())
+        .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)))
+    else {
+        return;
+    };
+
+    let mut rng = rand::thread_rng();
+    let weapons: Vec<Weapon> = Weapon::ALL
+        .into_iter()
+        .filter(|weapon| gun.has_ammo(*weapon))
+        .collect();
+    if let Some(weapon) = weapons.choose(&mut rng) {
+        gun.weapon = *weapon;
+    }
+
+    let sloppiness = rng.gen_range(-SLOPPINESS..=SLOPPINESS);
+    let force_scale = ai.force_scale.get(&gun.weapon).copied().unwrap_or(1.0);
+    let force = ideal_force(target.x - position.x, rapier_config.gravity.y)
+        * force_scale
+        * (1.0 + sloppiness);
+    // Angles are measured from the side facing the middle, opponents may be behind
+    gun.angle = if (target.x - position.x).signum() == aim::facing(position) {
+        THROW_ANGLE
+    } else {
+        180.0 - THROW_ANGLE
+    };
+    gun.target = aim::aim_target(position, gun.angle);
+    gun.force = force.min(charge.max_force);
+    ai.throw = Some(Throw {
+        shot: None,
+        weapon: gun.weapon,
+        from: position,
+        target,
+        last_seen: position,
+    });
+    fire.send(FireEvent);
+}
+
+/// Follows the shot of each computer player until it hits something, then corrects the
+/// force for the next throw.
+fn follow_throw(
+    mut collisions: EventReader<CollisionEvent>,
+    mut ai_query: Query<(Entity, &mut Ai)>,
+    new_shot_query: Query<(Entity, &Shot), Added<Shot>>,
+    shot_query: Query<&GlobalTransform, With<Shot>>,
+) {
+    let hit: Vec<Entity> = collisions
+        .read()
+        .filter_map(|event| match event {
+            CollisionEvent::Started(collider1, collider2, _) => Some([*collider1, *collider2]),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+
+    for (entity, mut ai) in ai_query.iter_mut() {
+        let Some(throw) = ai.throw.as_mut() else {
+            continue;
+        };
+        if throw.shot.is_none() {
+            throw.shot = new_shot_query
+                .iter()
+                .find(|(_, shot)| shot.shooter == entity)
+                .map(|(shot, _)| shot);
+        }
+        let Some(shot) = throw.shot else {
+            continue;
+        };
+
+        let landed = match shot_query.get(shot) {
+            Ok(transform) => {
+                throw.last_seen = transform.translation().truncate();
+                hit.contains(&shot)
+            }
+            // Gone without a collision, flown off or despawned
+            Err(_) => true,
+        };
+        if landed {
+            let wanted = throw.target.x - throw.from.x;
+            let landed = throw.last_seen.x - throw.from.x;
+            let weapon = throw.weapon;
+            let scale = ai.force_scale.entry(weapon).or_insert(1.0);
+            *scale = corrected_scale(*scale, wanted, landed);
+            ai.throw = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_throws_are_corrected() {
+        // Landing halfway needs twice the reach, so the square root of two times the force
+        let scale = corrected_scale(1.0, 100.0, 50.0);
+        assert!((scale - 2.0f32.sqrt()).abs() < 1e-5);
+        assert_eq!(corrected_scale(1.0, -100.0, -400.0), 0.5);
+        // Throws that went backwards teach nothing
+        assert_eq!(corrected_scale(1.0, 100.0, -50.0), 1.0);
+    }
+}
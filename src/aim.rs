@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+
+use crate::{
+    input::{Action, ActionAxis, ActionState, InputDevice},
+    player::{ChargeSettings, FireEvent, Gun},
+    turn::{ActivePlayer, TurnStarted},
+};
+
+/// Degrees per second the aim turns while an arrow key is held
+const AIM_SPEED: f32 = 45.0;
+/// Turn speed while Shift is held as well
+const FINE_AIM_SPEED: f32 = 5.0;
+/// Distance from the player to the aim target of the keyboard modes, the computer and replays
+pub(crate) const AIM_DISTANCE: f32 = 40.0;
+/// Longest number that can be typed in the classic mode
+const MAX_ENTRY_LEN: usize = 6;
+
+/// How a player aims their shots. [`Action::SwitchAimMode`] switches the mode of the player
+/// whose turn it is.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AimMode {
+    /// Aim at the cursor and hold Space to charge. Players on a gamepad turn the aim as in
+    /// the keyboard mode.
+    #[default]
+    Mouse,
+    /// Turn the aim with the aim axis and hold Space to charge
+    Keyboard,
+    /// Type the angle and velocity, like QBasic Gorillas
+    Classic,
+}
+
+impl AimMode {
+    fn next(self) -> Self {
+        match self {
+            AimMode::Mouse => AimMode::Keyboard,
+            AimMode::Keyboard => AimMode::Classic,
+            AimMode::Classic => AimMode::Mouse,
+        }
+    }
+}
+
+impl std::str::FromStr for AimMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mouse" => Ok(AimMode::Mouse),
+            "keyboard" => Ok(AimMode::Keyboard),
+            "classic" => Ok(AimMode::Classic),
+            _ => Err(format!("Unknown aim mode: {}", s)),
+        }
+    }
+}
+
+/// The aim target for an angle in degrees above the horizon, facing the middle of the board
+/// as the gorillas did in the original game.
+pub fn aim_target(position: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    position + Vec2::new(cos * facing(position), sin) * AIM_DISTANCE
+}
+
+/// `1.0` when a gorilla at `position` faces right towards the middle, `-1.0` when it faces
+/// left.
+pub fn facing(position: Vec2) -> f32 {
+    if position.x > 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// Text typed so far in the classic mode.
+#[derive(Resource, Debug, Default)]
+struct ClassicEntry {
+    angle: Option<f32>,
+    text: String,
+}
+
+pub struct AimPlugin;
+
+impl Plugin for AimPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClassicEntry>()
+            .add_systems(Startup, spawn_classic_hud)
+            .add_systems(
+                Update,
+                (
+                    switch_aim_mode,
+                    keyboard_aim,
+                    classic_input,
+                    update_classic_hud,
+                ),
+            );
+    }
+}
+
+fn switch_aim_mode(mut q_player: Query<(&mut AimMode, &ActionState), With<ActivePlayer>>) {
+    let Ok((mut mode, actions)) = q_player.get_single_mut() else {
+        return;
+    };
+    if actions.just_pressed(Action::SwitchAimMode) {
+        *mode = mode.next();
+        debug!(mode = ?*mode, "Switched aim mode");
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn keyboard_aim(
+    time: Res<Time>,
+    mut q_player: Query<
+        (
+            &GlobalTransform,
+            &AimMode,
+            &InputDevice,
+            &ActionState,
+            &mut Gun,
+        ),
+        With<ActivePlayer>,
+    >,
+) {
+    let Ok((transform, mode, device, actions, mut gun)) = q_player.get_single_mut() else {
+        return;
+    };
+    let turning = match mode {
+        AimMode::Keyboard => true,
+        AimMode::Mouse => *device != InputDevice::KeyboardMouse,
+        AimMode::Classic => false,
+    };
+    if !turning {
+        return;
+    }
+
+    let speed = if actions.pressed(Action::FineAim) {
+        FINE_AIM_SPEED
+    } else {
+        AIM_SPEED
+    };
+    gun.angle += actions.axis(ActionAxis::Aim) * speed * time.delta_seconds();
+    gun.angle = gun.angle.clamp(-90.0, 180.0);
+    gun.target = aim_target(transform.translation().truncate(), gun.angle);
+}
+
+/// Reads the angle and then the velocity, each confirmed with Enter, and fires.
+#[allow(clippy::too_many_arguments)]
+fn classic_input(
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard: Res<Input<KeyCode>>,
+    charge: Res<ChargeSettings>,
+    mut entry: ResMut<ClassicEntry>,
+    mut fire: EventWriter<FireEvent>,
+    mut turn_started: EventReader<TurnStarted>,
+    mut q_player: Query<(&GlobalTransform, &AimMode, &InputDevice, &mut Gun), With<ActivePlayer>>,
+) {
+    let typed: String = characters.read().map(|event| event.char).collect();
+    // A new turn starts over
+    if turn_started.read().count() > 0 {
+        *entry = ClassicEntry::default();
+    }
+    let Ok((transform, mode, device, mut gun)) = q_player.get_single_mut() else {
+        return;
+    };
+    // The computer types its own numbers
+    if *mode != AimMode::Classic || *device == InputDevice::Computer {
+        return;
+    }
+
+    for c in typed.chars() {
+        // Angles below the horizon are negative
+        let sign = c == '-' && entry.text.is_empty() && entry.angle.is_none();
+        if (c.is_ascii_digit() || c == '.' || sign) && entry.text.len() < MAX_ENTRY_LEN {
+            entry.text.push(c);
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Back) {
+        entry.text.pop();
+    }
+    if !keyboard.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    let Ok(value) = entry.text.parse::<f32>() else {
+        return;
+    };
+    entry.text.clear();
+    let position = transform.translation().truncate();
+    match entry.angle {
+        None => {
+            let angle = value.clamp(-90.0, 180.0);
+            entry.angle = Some(angle);
+            gun.angle = angle;
+            gun.target = aim_target(position, angle);
+        }
+        Some(_) => {
+            gun.force = value.min(charge.max_force);
+            fire.send(FireEvent);
+            entry.angle = None;
+        }
+    }
+}
+
+#[derive(Component)]
+struct ClassicHud;
+
+fn spawn_classic_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            top: Val::Px(8.0),
+            ..default()
+        }),
+        ClassicHud,
+    ));
+}
+
+fn update_classic_hud(
+    entry: Res<ClassicEntry>,
+    q_player: Query<&AimMode, With<ActivePlayer>>,
+    mut hud_query: Query<&mut Text, With<ClassicHud>>,
+) {
+    let label = match q_player.get_single() {
+        Ok(AimMode::Classic) => match entry.angle {
+            None => format!("Angle: {}_", entry.text),
+            Some(angle) => format!("Angle: {}\nVelocity: {}_", angle, entry.text),
+        },
+        _ => String::new(),
+    };
+    for mut text in hud_query.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aim_faces_the_middle() {
+        let left = aim_target(Vec2::new(-100.0, 0.0), 0.0);
+        assert!(left.x > -100.0);
+        let right = aim_target(Vec2::new(100.0, 0.0), 0.0);
+        assert!(right.x < 100.0);
+
+        let up = aim_target(Vec2::ZERO, 90.0);
+        assert!(up.x.abs() < 1e-3 && up.y > 0.0);
+    }
+}
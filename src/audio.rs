@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use bevy::{
+    audio::{SpatialScale, Volume},
+    ecs::system::SystemParam,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{CraterEvent, DamageEvent, FractureEvent},
+    config::Config,
+    player::Shot,
+    team::{PlayerDamageEvent, RoundOver},
+};
+
+/// Distance between the ears of the listener, in pixels
+const EAR_GAP: f32 = 100.0;
+/// Damage to a brick that plays an impact at full volume
+const LOUD_IMPACT: f32 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sound {
+    Throw,
+    /// A shot or falling brick hitting a brick
+    Impact,
+    /// A shot blowing a crater into the terrain
+    Explosion,
+    /// A brick breaking apart
+    Fracture,
+    /// A gorilla getting hurt
+    Hit,
+    Victory,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Between `0.0` and `1.0`
+    pub volume: f32,
+    pub muted: bool,
+    /// Sound files relative to the assets folder, events without one are silent
+    pub sounds: HashMap<Sound, String>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        let sounds = [
+            (Sound::Throw, "sounds/throw.wav"),
+            (Sound::Impact, "sounds/impact.wav"),
+            (Sound::Explosion, "sounds/explosion.wav"),
+            (Sound::Fracture, "sounds/fracture.wav"),
+            (Sound::Hit, "sounds/hit.wav"),
+            (Sound::Victory, "sounds/victory.wav"),
+        ]
+        .into_iter()
+        .map(|(sound, path)| (sound, path.to_string()))
+        .collect();
+
+        Self {
+            volume: 0.8,
+            muted: false,
+            sounds,
+        }
+    }
+}
+
+/// Volume and mute state, M toggles mute.
+#[derive(Resource, Debug, Default)]
+pub struct AudioSettings {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+#[derive(Resource, Debug, Default)]
+struct Sounds(HashMap<Sound, Handle<AudioSource>>);
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        // Pixels are far too small as units for the default spatial scale
+        app.insert_resource(SpatialScale::new_2d(1.0 / EAR_GAP))
+            .init_resource::<AudioSettings>()
+            .init_resource::<Sounds>()
+            .add_systems(Startup, load_sounds)
+            .add_systems(
+                Update,
+                (
+                    add_listener,
+                    toggle_mute,
+                    play_board_sounds,
+                    play_game_sounds,
+                ),
+            );
+    }
+}
+
+fn load_sounds(
+    config: Res<Config>,
+    asset_server: Res<AssetServer>,
+    mut settings: ResMut<AudioSettings>,
+    mut sounds: ResMut<Sounds>,
+) {
+    settings.volume = config.audio.volume.clamp(0.0, 1.0);
+    settings.muted = config.audio.muted;
+    for (sound, path) in &config.audio.sounds {
+        sounds.0.insert(*sound, asset_server.load(path.clone()));
+    }
+}
+
+/// Listens from the camera, so sounds are panned by where they happen on screen.
+fn add_listener(
+    mut commands: Commands,
+    camera_query: Query<Entity, (With<Camera>, Without<SpatialListener>)>,
+) {
+    for camera in camera_query.iter() {
+        commands
+            .entity(camera)
+            .insert(SpatialListener::new(EAR_GAP));
+    }
+}
+
+fn toggle_mute(
+    keyboard: Res<Input<KeyCode>>,
+    mut settings: ResMut<AudioSettings>,
+    sink_query: Query<&AudioSink>,
+    spatial_sink_query: Query<&SpatialAudioSink>,
+) {
+    if !keyboard.just_pressed(KeyCode::M) {
+        return;
+    }
+    settings.muted = !settings.muted;
+    info!(muted = settings.muted, "Toggled sound");
+    let sinks = sink_query
+        .iter()
+        .map(|sink| sink as &dyn AudioSinkPlayback)
+        .chain(
+            spatial_sink_query
+                .iter()
+                .map(|sink| sink as &dyn AudioSinkPlayback),
+        );
+    for sink in sinks {
+        if settings.muted {
+            sink.pause();
+        } else {
+            sink.play();
+        }
+    }
+}
+
+/// Plays at most one of each sound per frame, the loudest one.
+#[derive(SystemParam)]
+struct SoundPlayer<'w, 's> {
+    commands: Commands<'w, 's>,
+    settings: Res<'w, AudioSettings>,
+    sounds: Res<'w, Sounds>,
+    queued: Local<'s, HashMap<Sound, (Option<Vec2>, f32)>>,
+}
+
+impl SoundPlayer<'_, '_> {
+    /// Queues `sound` at `volume`, panned by `position` when given.
+    fn queue(&mut self, sound: Sound, position: Option<Vec2>, volume: f32) {
+        let queued = self.queued.entry(sound).or_insert((position, volume));
+        if volume > queued.1 {
+            *queued = (position, volume);
+        }
+    }
+
+    fn play(&mut self) {
+        let queued = std::mem::take(&mut *self.queued);
+        if self.settings.muted {
+            return;
+        }
+        for (sound, (position, volume)) in queued {
+            let Some(source) = self.sounds.0.get(&sound) else {
+                continue;
+            };
+            let settings = PlaybackSettings::DESPAWN
+                .with_volume(Volume::new_relative(self.settings.volume * volume))
+                .with_spatial(position.is_some());
+            let position = position.unwrap_or_default();
+            self.commands.spawn((
+                AudioBundle {
+                    source: source.clone(),
+                    settings,
+                },
+                TransformBundle::from_transform(Transform::from_xyz(position.x, position.y, 0.0)),
+            ));
+        }
+    }
+}
+
+fn play_board_sounds(
+    mut player: SoundPlayer,
+    mut damage_reader: EventReader<DamageEvent>,
+    mut fracture_reader: EventReader<FractureEvent>,
+    mut crater_reader: EventReader<CraterEvent>,
+    transform_query: Query<&GlobalTransform>,
+) {
+    // Bricks may already be gone, they are heard without panning then
+    let position = |entity| {
+        transform_query
+            .get(entity)
+            .ok()
+            .map(|transform| transform.translation().truncate())
+    };
+    for event in damage_reader.read() {
+        let volume = (event.damage / LOUD_IMPACT).clamp(0.2, 1.0);
+        player.queue(Sound::Impact, position(event.entity), volume);
+    }
+    for event in fracture_reader.read() {
+        player.queue(Sound::Fracture, position(event.0), 1.0);
+    }
+    for event in crater_reader.read() {
+        player.queue(Sound::Explosion, Some(event.position), 1.0);
+    }
+    player.play();
+}
+
+fn play_game_sounds(
+    mut player: SoundPlayer,
+    shot_query: Query<&GlobalTransform, Added<Shot>>,
+    mut hit_reader: EventReader<PlayerDamageEvent>,
+    mut round_over_reader: EventReader<RoundOver>,
+    transform_query: Query<&GlobalTransform>,
+) {
+    for transform in shot_query.iter() {
+        player.queue(Sound::Throw, Some(transform.translation().truncate()), 1.0);
+    }
+    for event in hit_reader.read() {
+        let position = transform_query
+            .get(event.player)
+            .ok()
+            .map(|transform| transform.translation().truncate());
+        player.queue(Sound::Hit, position, 1.0);
+    }
+    if round_over_reader.read().count() > 0 {
+        player.queue(Sound::Victory, None, 1.0);
+    }
+    player.play();
+}
@@ -0,0 +1,428 @@
+//! Plays many headless computer-vs-computer matches in parallel and reports who wins from
+//! which spawn position, on which level, and how often throwers of each weapon win.
+//!
+//! Every match is a run of the game binary with `--headless --stats`, so a crashed or stuck
+//! match cannot take the others down.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{mpsc, Mutex},
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+/// Decisive rounds a level needs before it is judged
+const MIN_ROUNDS: u32 = 20;
+/// Standard score beyond which a win rate is too far from a fair share to be chance, about
+/// a 1% two-sided test
+const UNFAIR_Z: f32 = 2.58;
+
+#[derive(Parser, Debug)]
+#[command(about = "Runs headless AI-vs-AI matches and reports win rates")]
+struct Args {
+    /// LDtk projects to play, relative to the assets folder
+    #[arg(long, num_args = 1..)]
+    levels: Vec<String>,
+    /// Number of random skylines to play as well, one match each
+    #[arg(long, default_value_t = 0)]
+    seeds: u64,
+    /// Seed of the first random skyline
+    #[arg(long, default_value_t = 0)]
+    first_seed: u64,
+    /// Matches per level and weapon set
+    #[arg(long, default_value_t = 10)]
+    matches: u32,
+    /// Rounds per match
+    #[arg(long, default_value_t = 1)]
+    rounds: u32,
+    /// Weapon sets to play with. Results are reported per weapon thrown, whatever the set.
+    #[arg(long, num_args = 1.., default_values_t = [String::from("full")])]
+    weapons: Vec<String>,
+    /// Matches played at the same time, the number of CPUs by default
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Seconds before a match is given up on
+    #[arg(long, default_value_t = 300)]
+    timeout: u64,
+    /// Game binary, `gorillas` next to this one by default
+    #[arg(long)]
+    game: Option<PathBuf>,
+    /// File to write the results to as JSON
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+/// What to play in one run of the game.
+#[derive(Debug, Clone)]
+struct Job {
+    id: usize,
+    level: Source,
+    weapons: String,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Level(String),
+    Seed(u64),
+}
+
+impl Source {
+    /// Name results are grouped under. Random skylines are all different, so they are
+    /// judged together.
+    fn name(&self) -> String {
+        match self {
+            Source::Level(path) => path.clone(),
+            Source::Seed(_) => "random skylines".to_string(),
+        }
+    }
+}
+
+/// The parts of the game's `--stats` report used here.
+#[derive(Debug, Deserialize)]
+struct MatchReport {
+    round_winners: Vec<Option<String>>,
+    /// `None` for a draw
+    winner: Option<String>,
+    players: Vec<PlayerReport>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlayerReport {
+    /// Identifier of the spawn point in the level
+    player: String,
+    team: String,
+    /// Shots and hits of each weapon thrown
+    weapons: BTreeMap<String, WeaponUse>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct WeaponUse {
+    shots: u32,
+    hits: u32,
+}
+
+/// Matches won by the gorillas that threw a weapon.
+#[derive(Debug, Clone, Default, Serialize)]
+struct WeaponTally {
+    /// Gorillas that threw the weapon at least once in a match, counted per match
+    throwers: u32,
+    /// Of those, the ones whose team won the match
+    wins: u32,
+    shots: u32,
+    hits: u32,
+}
+
+impl WeaponTally {
+    fn win_rate(&self) -> f32 {
+        self.wins as f32 / self.throwers.max(1) as f32
+    }
+
+    fn accuracy(&self) -> f32 {
+        self.hits as f32 / self.shots.max(1) as f32
+    }
+}
+
+/// Rounds won from each spawn position.
+#[derive(Debug, Clone, Default, Serialize)]
+struct Tally {
+    rounds: u32,
+    draws: u32,
+    wins: BTreeMap<String, u32>,
+}
+
+impl Tally {
+    fn add(&mut self, report: &MatchReport) {
+        for player in &report.players {
+            self.wins.entry(player.player.clone()).or_default();
+        }
+        for winner in &report.round_winners {
+            self.rounds += 1;
+            let Some(team) = winner else {
+                self.draws += 1;
+                continue;
+            };
+            for player in report.players.iter().filter(|player| player.team == *team) {
+                *self.wins.entry(player.player.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    fn win_rate(&self, position: &str) -> f32 {
+        let wins = self.wins.get(position).copied().unwrap_or_default();
+        wins as f32 / self.rounds.max(1) as f32
+    }
+
+    /// Spawn positions that win significantly less often than a fair share of the
+    /// decisive rounds. `None` when there are too few rounds to tell.
+    fn disadvantaged(&self) -> Option<Vec<String>> {
+        let decisive = self.rounds - self.draws;
+        let positions = self.wins.len();
+        if decisive < MIN_ROUNDS || positions < 2 {
+            return None;
+        }
+        let fair = 1.0 / positions as f32;
+        let deviation = (decisive as f32 * fair * (1.0 - fair)).sqrt();
+        Some(
+            self.wins
+                .iter()
+                .filter(|(_, wins)| {
+                    (**wins as f32 - decisive as f32 * fair) / deviation < -UNFAIR_Z
+                })
+                .map(|(position, _)| position.clone())
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Results {
+    matches: u32,
+    failed: u32,
+    overall: Tally,
+    levels: BTreeMap<String, Tally>,
+    weapons: BTreeMap<String, WeaponTally>,
+}
+
+impl Results {
+    fn add(&mut self, job: &Job, report: &MatchReport) {
+        self.matches += 1;
+        self.overall.add(report);
+        self.levels.entry(job.level.name()).or_default().add(report);
+        for player in &report.players {
+            let won = report.winner.as_ref() == Some(&player.team);
+            for (weapon, used) in player.weapons.iter().filter(|(_, used)| used.shots > 0) {
+                let tally = self.weapons.entry(weapon.clone()).or_default();
+                tally.throwers += 1;
+                tally.wins += u32::from(won);
+                tally.shots += used.shots;
+                tally.hits += used.hits;
+            }
+        }
+    }
+}
+
+fn jobs(args: &Args) -> Vec<Job> {
+    let mut sources = Vec::new();
+    for level in &args.levels {
+        sources.extend((0..args.matches).map(|_| Source::Level(level.clone())));
+    }
+    sources.extend((args.first_seed..args.first_seed + args.seeds).map(Source::Seed));
+
+    let mut jobs = Vec::new();
+    for level in sources {
+        for weapons in &args.weapons {
+            jobs.push(Job {
+                id: jobs.len(),
+                level: level.clone(),
+                weapons: weapons.clone(),
+            });
+        }
+    }
+    jobs
+}
+
+/// Plays one match and reads its report.
+fn play(args: &Args, game: &Path, stats_dir: &Path, job: &Job) -> Result<MatchReport, String> {
+    let stats = stats_dir.join(format!("{}.json", job.id));
+    let mut command = Command::new(game);
+    command
+        .args(["--headless", "--log", "warn"])
+        .arg("--rounds")
+        .arg(args.rounds.to_string())
+        .arg("--weapons")
+        .arg(&job.weapons)
+        .arg("--stats")
+        .arg(&stats)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    match &job.level {
+        Source::Level(path) => command.arg("--level").arg(path),
+        Source::Seed(seed) => command.arg("--seed").arg(seed.to_string()),
+    };
+
+    let mut child = command.spawn().map_err(|err| err.to_string())?;
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|err| err.to_string())? {
+            break status;
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err("timed out".to_string());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    if !status.success() {
+        return Err(format!("exited with {}", status));
+    }
+
+    let file = std::fs::File::open(&stats).map_err(|err| err.to_string())?;
+    let report = serde_json::from_reader(std::io::BufReader::new(file));
+    let _ = std::fs::remove_file(&stats);
+    report.map_err(|err| err.to_string())
+}
+
+fn print_tally(name: &str, tally: &Tally) {
+    println!("{} ({} rounds, {} draws)", name, tally.rounds, tally.draws);
+    for (position, wins) in &tally.wins {
+        println!(
+            "  {:<16} {:>6} wins {:>6.1}%",
+            position,
+            wins,
+            tally.win_rate(position) * 100.0
+        );
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let game = args.game.clone().unwrap_or_else(|| {
+        std::env::current_exe()
+            .expect("Failed to find the tournament binary")
+            .with_file_name(format!("gorillas{}", std::env::consts::EXE_SUFFIX))
+    });
+    let stats_dir =
+        std::env::temp_dir().join(format!("gorillas-tournament-{}", std::process::id()));
+    if let Err(err) = std::fs::create_dir_all(&stats_dir) {
+        eprintln!("Failed to create {}: {}", stats_dir.display(), err);
+        std::process::exit(1);
+    }
+
+    let jobs = jobs(&args);
+    let total = jobs.len();
+    let threads = args
+        .threads
+        .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .clamp(1, total.max(1));
+    eprintln!("Playing {} matches on {} threads", total, threads);
+
+    let queue = Mutex::new(VecDeque::from(jobs));
+    let (sender, receiver) = mpsc::channel();
+    let mut results = Results::default();
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (args, game, stats_dir, queue) = (&args, &game, &stats_dir, &queue);
+            scope.spawn(move || loop {
+                let Some(job) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let report = play(args, game, stats_dir, &job);
+                if sender.send((job, report)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (done, (job, report)) in receiver.iter().enumerate() {
+            match report {
+                Ok(report) => results.add(&job, &report),
+                Err(err) => {
+                    results.failed += 1;
+                    eprintln!("Match {} on {} failed: {}", job.id, job.level.name(), err);
+                }
+            }
+            eprint!("\r{}/{} matches", done + 1, total);
+        }
+        eprintln!();
+    });
+    let _ = std::fs::remove_dir(&stats_dir);
+
+    println!(
+        "{} matches played, {} failed\n",
+        results.matches, results.failed
+    );
+    print_tally("All levels", &results.overall);
+    println!();
+    for (weapon, tally) in &results.weapons {
+        println!(
+            "Weapon {:<10} {:>6} throwers {:>6.1}% won {:>6.1}% accuracy",
+            weapon,
+            tally.throwers,
+            tally.win_rate() * 100.0,
+            tally.accuracy() * 100.0
+        );
+    }
+    println!();
+    for (level, tally) in &results.levels {
+        print_tally(level, tally);
+        match tally.disadvantaged() {
+            None => println!("  Not enough decisive rounds to judge"),
+            Some(unfair) if unfair.is_empty() => println!("  Fair"),
+            Some(unfair) => println!("  Unfair to {}", unfair.join(", ")),
+        }
+    }
+
+    if let Some(path) = &args.out {
+        let written = std::fs::File::create(path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                serde_json::to_writer_pretty(file, &results).map_err(|err| err.to_string())
+            });
+        if let Err(err) = written {
+            eprintln!("Failed to write {}: {}", path.display(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(winner: &str) -> MatchReport {
+        MatchReport {
+            round_winners: vec![Some(winner.to_string())],
+            winner: Some(winner.to_string()),
+            players: ["A", "B"]
+                .map(|team| PlayerReport {
+                    player: format!("Player{}", team),
+                    team: team.to_string(),
+                    weapons: BTreeMap::from([(
+                        if team == "A" { "bunch" } else { "banana" }.to_string(),
+                        WeaponUse { shots: 2, hits: 1 },
+                    )]),
+                })
+                .into(),
+        }
+    }
+
+    #[test]
+    fn lopsided_levels_are_unfair() {
+        let mut tally = Tally::default();
+        for round in 0..40 {
+            tally.add(&report(if round % 2 == 0 { "A" } else { "B" }));
+        }
+        assert_eq!(tally.disadvantaged(), Some(vec![]));
+
+        let mut tally = Tally::default();
+        for round in 0..40 {
+            tally.add(&report(if round % 5 == 0 { "B" } else { "A" }));
+        }
+        assert_eq!(tally.disadvantaged(), Some(vec!["PlayerB".to_string()]));
+    }
+
+    #[test]
+    fn weapons_are_tallied_per_thrower() {
+        let job = Job {
+            id: 0,
+            level: Source::Seed(0),
+            weapons: "full".to_string(),
+        };
+        let mut results = Results::default();
+        for round in 0..4 {
+            results.add(&job, &report(if round == 0 { "B" } else { "A" }));
+        }
+        let bunch = &results.weapons["bunch"];
+        assert_eq!((bunch.throwers, bunch.wins, bunch.shots), (4, 3, 8));
+        assert_eq!(bunch.win_rate(), 0.75);
+        assert_eq!(results.weapons["banana"].win_rate(), 0.25);
+        assert_eq!(bunch.accuracy(), 0.5);
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::Sleeping;
+
+/// How bricks break apart and how long the pieces stay around.
+#[derive(Resource, Debug, Clone)]
+pub struct FractureSettings {
+    /// Bricks whose fragments would be smaller than this crumble away instead of splitting
+    pub min_fragment_size: f32,
+    /// Fraction of its parent's full health a fragment starts with
+    pub fragment_health: f32,
+    /// Seconds a fragment stays before fading out, unless it comes to rest earlier
+    pub lifetime: f32,
+    /// Seconds spent fading out
+    pub fade_time: f32,
+    /// Most fragments alive at once, the oldest are removed first
+    pub max_debris: usize,
+}
+
+impl Default for FractureSettings {
+    fn default() -> Self {
+        Self {
+            min_fragment_size: 4.0,
+            fragment_health: 0.5,
+            lifetime: 10.0,
+            fade_time: 1.0,
+            max_debris: 200,
+        }
+    }
+}
+
+/// A fragment of a fractured brick.
+#[derive(Component, Debug)]
+pub struct Debris {
+    lifetime: Timer,
+    fade: Timer,
+}
+
+impl Debris {
+    pub fn new(settings: &FractureSettings) -> Self {
+        Self {
+            lifetime: Timer::from_seconds(settings.lifetime, TimerMode::Once),
+            fade: Timer::from_seconds(settings.fade_time, TimerMode::Once),
+        }
+    }
+}
+
+/// Fades out fragments that outlived their lifetime or came to rest, then despawns them.
+pub fn age_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Debris, &mut Sprite, Option<&Sleeping>)>,
+) {
+    for (entity, mut debris, mut sprite, sleeping) in query.iter_mut() {
+        debris.lifetime.tick(time.delta());
+        let asleep = sleeping.is_some_and(|sleeping| sleeping.sleeping);
+        if !debris.lifetime.finished() && !asleep {
+            continue;
+        }
+
+        debris.fade.tick(time.delta());
+        sprite.color.set_a(debris.fade.percent_left());
+        if debris.fade.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Keeps the number of fragments under [`FractureSettings::max_debris`] by expiring the
+/// oldest ones, which [`age_debris`] then removes.
+pub fn limit_debris(settings: Res<FractureSettings>, mut query: Query<&mut Debris>) {
+    let count = query.iter().len();
+    if count <= settings.max_debris {
+        return;
+    }
+
+    let mut debris: Vec<_> = query.iter_mut().collect();
+    debris.sort_by_key(|debris| std::cmp::Reverse(debris.lifetime.elapsed()));
+    for debris in debris.iter_mut().take(count - settings.max_debris) {
+        let lifetime = debris.lifetime.duration();
+        let fade_time = debris.fade.duration();
+        debris.lifetime.set_elapsed(lifetime);
+        debris.fade.set_elapsed(fade_time);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    dynamics::{RigidBody, Sleeping, Velocity},
+    geometry::ActiveEvents,
+};
+
+use super::BoardBrick;
+
+/// Slowest impact that damages the brick landed on, in pixels per second
+const MIN_IMPACT_SPEED: f32 = 20.0;
+/// Damage dealt per pixel per second of impact speed
+const IMPACT_DAMAGE: f32 = 0.5;
+
+/// A brick that is no longer part of the fixed board, either because it lost its connection
+/// to the ground or because it is a fragment of a fractured brick.
+#[derive(Component, Debug, Default)]
+pub struct FallingBrick {
+    /// Speed before the last physics step, as collisions are reported after the impact has
+    /// already slowed the brick down.
+    speed: f32,
+}
+
+impl FallingBrick {
+    pub fn impact_damage(&self) -> Option<f32> {
+        (self.speed >= MIN_IMPACT_SPEED).then_some(self.speed * IMPACT_DAMAGE)
+    }
+}
+
+/// A brick of the fixed board was removed, so the bricks it held up may have to fall.
+/// Falling bricks and debris hold nothing up and are not reported.
+#[derive(Event, Debug)]
+pub struct SupportRemoved(pub Entity);
+
+/// Finds the bricks no longer connected to an anchored brick through their neighbours and
+/// lets them fall. Runs whenever bricks have been removed from the fixed board.
+pub fn collapse_unsupported(
+    mut commands: Commands,
+    mut removed_reader: EventReader<SupportRemoved>,
+    brick_query: Query<(Entity, &BoardBrick), Without<FallingBrick>>,
+) {
+    let removed: HashSet<Entity> = removed_reader.read().map(|event| event.0).collect();
+    if removed.is_empty() {
+        return;
+    }
+
+    // The removed bricks are only despawned at the end of the frame
+    let cells: HashMap<IVec2, (Entity, bool)> = brick_query
+        .iter()
+        .filter(|(entity, _)| !removed.contains(entity))
+        .map(|(entity, brick)| (brick.cell, (entity, brick.anchored)))
+        .collect();
+
+    for entity in unsupported(&cells) {
+        commands
+            .entity(entity)
+            .insert(RigidBody::Dynamic)
+            .insert(Velocity::default())
+            .insert(Sleeping::default())
+            .insert(ActiveEvents::COLLISION_EVENTS)
+            .insert(FallingBrick::default());
+    }
+}
+
+/// Flood fills from the anchored cells and returns the entities that were not reached.
+fn unsupported<T: Copy>(cells: &HashMap<IVec2, (T, bool)>) -> Vec<T> {
+    let mut supported: HashSet<IVec2> = HashSet::new();
+    let mut queue: VecDeque<IVec2> = cells
+        .iter()
+        .filter(|(_, (_, anchored))| *anchored)
+        .map(|(cell, _)| *cell)
+        .collect();
+
+    while let Some(cell) = queue.pop_front() {
+        if !cells.contains_key(&cell) || !supported.insert(cell) {
+            continue;
+        }
+        for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            queue.push_back(cell + offset);
+        }
+    }
+
+    cells
+        .iter()
+        .filter(|(cell, _)| !supported.contains(cell))
+        .map(|(_, (entity, _))| *entity)
+        .collect()
+}
+
+pub fn track_falling_speed(mut query: Query<(&mut FallingBrick, &Velocity)>) {
+    for (mut falling, velocity) in query.iter_mut() {
+        falling.speed = velocity.linvel.length();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floating_bricks_are_unsupported() {
+        // A pillar on an anchored floor, with a ledge hanging off it and a floating brick
+        let mut cells = HashMap::new();
+        for x in 0..3 {
+            cells.insert(IVec2::new(x, 3), ("floor", true));
+        }
+        cells.insert(IVec2::new(1, 2), ("pillar", false));
+        cells.insert(IVec2::new(1, 1), ("pillar", false));
+        cells.insert(IVec2::new(2, 1), ("ledge", false));
+        cells.insert(IVec2::new(0, 0), ("floating", false));
+
+        assert_eq!(unsupported(&cells), vec!["floating"]);
+
+        cells.remove(&IVec2::new(1, 2));
+        let mut falling = unsupported(&cells);
+        falling.sort();
+        assert_eq!(falling, vec!["floating", "ledge", "pillar"]);
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use super::particles::ParticleKind;
+
+/// How a brick splits when its health runs out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FracturePattern {
+    /// Four fragments, half the size along each axis
+    Quarters,
+    /// Two fragments, split across the longer side
+    Halves,
+    /// Sixteen small shards
+    Shatter,
+    /// No fragments, the brick is just removed
+    Crumble,
+}
+
+impl FracturePattern {
+    /// Number of fragments along each axis for a brick of the given size.
+    pub fn grid(self, size: Vec2) -> UVec2 {
+        match self {
+            FracturePattern::Quarters => UVec2::new(2, 2),
+            FracturePattern::Halves if size.x >= size.y => UVec2::new(2, 1),
+            FracturePattern::Halves => UVec2::new(1, 2),
+            FracturePattern::Shatter => UVec2::new(4, 4),
+            FracturePattern::Crumble => UVec2::ZERO,
+        }
+    }
+}
+
+/// Physical and visual properties of a `BrickType` from the LDtk project.
+#[derive(Debug, Clone)]
+pub struct BrickMaterial {
+    pub health: f32,
+    pub density: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    /// Fraction of incoming damage that is ignored, `1.0` makes the brick indestructible
+    pub damage_resistance: f32,
+    pub fracture: FracturePattern,
+    pub color: Color,
+    /// Image drawn on the brick, tinted by `color`
+    pub texture: Option<String>,
+    /// Thrown off the brick when it is hit or breaks
+    pub particles: ParticleKind,
+}
+
+impl BrickMaterial {
+    pub fn is_indestructible(&self) -> bool {
+        self.damage_resistance >= 1.0
+    }
+
+    pub fn damage(&self, damage: f32) -> f32 {
+        damage * (1.0 - self.damage_resistance).max(0.0)
+    }
+}
+
+/// Materials by `BrickType` enum value. Tiles are matched through the tileset enum tags,
+/// IntGrid cells through the identifier of their value.
+#[derive(Resource, Debug, Clone)]
+pub struct BrickMaterials {
+    pub materials: HashMap<String, BrickMaterial>,
+    /// Used for bricks without a known `BrickType`
+    pub fallback: String,
+}
+
+impl Default for BrickMaterials {
+    fn default() -> Self {
+        let materials = [
+            (
+                "Destructable",
+                BrickMaterial {
+                    health: 100.0,
+                    density: 1.0,
+                    friction: 0.5,
+                    restitution: 0.1,
+                    damage_resistance: 0.0,
+                    fracture: FracturePattern::Quarters,
+                    color: Color::rgb(0.0, 1.0, 0.0),
+                    texture: None,
+                    particles: ParticleKind::Dust,
+                },
+            ),
+            (
+                "Window",
+                BrickMaterial {
+                    health: 25.0,
+                    density: 0.5,
+                    friction: 0.2,
+                    restitution: 0.3,
+                    damage_resistance: 0.0,
+                    fracture: FracturePattern::Shatter,
+                    color: Color::rgb(1.0, 1.0, 0.4),
+                    texture: None,
+                    particles: ParticleKind::Sparks,
+                },
+            ),
+            (
+                "Steel",
+                BrickMaterial {
+                    health: 150.0,
+                    density: 4.0,
+                    friction: 0.4,
+                    restitution: 0.05,
+                    damage_resistance: 0.5,
+                    fracture: FracturePattern::Halves,
+                    color: Color::rgb(0.45, 0.5, 0.55),
+                    texture: None,
+                    particles: ParticleKind::Sparks,
+                },
+            ),
+            (
+                "Indestructible",
+                BrickMaterial {
+                    health: 100.0,
+                    density: 5.0,
+                    friction: 0.8,
+                    restitution: 0.0,
+                    damage_resistance: 1.0,
+                    fracture: FracturePattern::Crumble,
+                    color: Color::rgb(0.25, 0.25, 0.25),
+                    texture: None,
+                    particles: ParticleKind::Dust,
+                },
+            ),
+        ]
+        .into_iter()
+        .map(|(id, material)| (id.to_string(), material))
+        .collect();
+
+        Self {
+            materials,
+            fallback: "Destructable".to_string(),
+        }
+    }
+}
+
+impl BrickMaterials {
+    /// The material of the first `BrickType` in `types` that has one.
+    pub fn get<'a>(&self, types: impl IntoIterator<Item = &'a str>) -> &BrickMaterial {
+        types
+            .into_iter()
+            .find_map(|id| self.materials.get(id))
+            .unwrap_or_else(|| &self.materials[&self.fallback])
+    }
+}
@@ -0,0 +1,515 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    dynamics::{RigidBody, Sleeping, Velocity},
+    geometry::{ActiveEvents, Collider, ColliderMassProperties, Friction, Restitution},
+    pipeline::CollisionEvent,
+};
+
+use crate::{
+    ldtk::{self, convert_coords, LdtkAsset},
+    menu::AppState,
+    player::Shot,
+    Board,
+};
+
+mod debris;
+mod integrity;
+mod material;
+mod particles;
+mod terrain;
+
+pub use debris::FractureSettings;
+pub use integrity::FallingBrick;
+pub use material::{BrickMaterial, BrickMaterials};
+pub use particles::ParticleSettings;
+pub use terrain::{CraterEvent, TerrainSettings};
+
+use debris::Debris;
+use integrity::SupportRemoved;
+use particles::Particle;
+use terrain::{Terrain, TerrainChunk, TerrainSprite};
+
+pub struct BoardPlugin;
+
+impl Plugin for BoardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BrickMaterials>()
+            .init_resource::<FractureSettings>()
+            .init_resource::<TerrainSettings>()
+            .init_resource::<ParticleSettings>()
+            .add_event::<FractureEvent>()
+            .add_event::<SupportRemoved>()
+            .add_event::<DamageEvent>()
+            .add_event::<CraterEvent>()
+            .add_systems(Update, spawn_board)
+            .add_systems(OnExit(AppState::Playing), clear_board)
+            .add_systems(Update, read_colisions)
+            .add_systems(Update, handle_fracture)
+            .add_systems(Update, handle_health)
+            .add_systems(
+                Update,
+                (
+                    integrity::collapse_unsupported.after(handle_fracture),
+                    integrity::track_falling_speed,
+                    (debris::limit_debris, debris::age_debris).chain(),
+                    particles::emit_particles.after(handle_health),
+                    particles::update_particles,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    terrain::read_crater_collisions,
+                    terrain::carve_craters,
+                    terrain::rebuild_dirty_chunks,
+                )
+                    .chain()
+                    .run_if(resource_exists::<Terrain>()),
+            );
+    }
+}
+
+fn spawn_board(
+    mut commands: Commands,
+    mut board: ResMut<Board>,
+    maps: Res<Assets<LdtkAsset>>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    materials: Res<BrickMaterials>,
+    terrain_settings: Res<TerrainSettings>,
+) {
+    // Board is already loaded or the map is not loaded yet
+    if board.loaded || maps.get(&board.map).is_none() {
+        return;
+    }
+
+    info!(
+        level = board.level,
+        terrain = board.terrain,
+        "Spawning board"
+    );
+
+    let map = maps.get(&board.map).expect("Failed to load map");
+    let project = &map.project;
+
+    let level = &project.levels[board.level];
+    let level_width = level.px_wid as f32;
+    let level_height = level.px_hei as f32;
+    let level_size = Vec2::new(level_width, level_height);
+    if board.terrain {
+        terrain::spawn_terrain(
+            &mut commands,
+            &mut images,
+            &materials,
+            project,
+            level,
+            &terrain_settings,
+        );
+    } else {
+        // Use the first tile layer
+        let layers = level.layer_instances.as_ref().expect("No layers");
+        for (index, layer) in layers.iter().enumerate() {
+            spawn_layer(
+                &mut commands,
+                &asset_server,
+                &materials,
+                project,
+                level_size,
+                index,
+                layer,
+            );
+        }
+    }
+
+    board.loaded = true;
+}
+
+/// Removes the bricks, terrain and particles at the end of a round.
+#[allow(clippy::type_complexity)]
+fn clear_board(
+    mut commands: Commands,
+    query: Query<
+        Entity,
+        Or<(
+            With<BoardBrick>,
+            With<TerrainChunk>,
+            With<TerrainSprite>,
+            With<Particle>,
+        )>,
+    >,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Terrain>();
+}
+
+/// Where a brick came from in the LDtk level, so it can be written back on export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrickSource {
+    /// A tile of the tile layer at index `layer` in the level
+    Tile { layer: usize, tile: i64 },
+    /// A cell of the IntGrid layer at index `layer` in the level
+    IntGrid { layer: usize, value: i64 },
+}
+
+/// A brick cell of a tile or IntGrid layer.
+struct LayerBrick<'a> {
+    px: Vec<i64>,
+    source: BrickSource,
+    /// `BrickType` values of the cell
+    types: Vec<&'a str>,
+}
+
+/// Bricks of `layer`, the layer at index `layer_index` in its level.
+fn layer_bricks<'a>(
+    project: &'a ldtk::Project,
+    layer_index: usize,
+    layer: &'a ldtk::LayerInstance,
+) -> Vec<LayerBrick<'a>> {
+    let tiles = layer.grid_tiles.iter().map(|tile| LayerBrick {
+        px: tile.px.clone(),
+        source: BrickSource::Tile {
+            layer: layer_index,
+            tile: tile.t,
+        },
+        types: layer
+            .tileset_def_uid
+            .map(|tileset| ldtk::tile_enum_tags(project, tileset, tile.t))
+            .unwrap_or_default(),
+    });
+
+    let cells = layer
+        .int_grid_csv
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| **value != 0)
+        .map(|(index, value)| {
+            let index = index as i64;
+            let cell = [index % layer.c_wid, index / layer.c_wid];
+            LayerBrick {
+                px: vec![cell[0] * layer.grid_size, cell[1] * layer.grid_size],
+                source: BrickSource::IntGrid {
+                    layer: layer_index,
+                    value: *value,
+                },
+                types: ldtk::int_grid_value_identifier(project, layer.layer_def_uid, *value)
+                    .into_iter()
+                    .collect(),
+            }
+        });
+
+    tiles.chain(cells).collect()
+}
+
+fn spawn_layer(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    materials: &BrickMaterials,
+    project: &ldtk::Project,
+    level_size: Vec2,
+    layer_index: usize,
+    layer: &ldtk::LayerInstance,
+) {
+    let grid_size = layer.grid_size as f32;
+    let brick_size = Vec2::new(grid_size, grid_size);
+
+    // Spawn tiles
+    for layer_brick in layer_bricks(project, layer_index, layer) {
+        let position = convert_coords(&layer_brick.px, grid_size, level_size);
+        let cell = IVec2::new(
+            (layer_brick.px[0] / layer.grid_size) as i32,
+            (layer_brick.px[1] / layer.grid_size) as i32,
+        );
+        let material = materials.get(layer_brick.types.iter().copied());
+        let mut brick = BoardBrick::new(material.clone(), layer_brick.source, cell);
+        // Bricks resting on the bottom of the level, or tagged as anchors, hold up the rest
+        brick.anchored = cell.y as i64 == layer.c_hei - 1 || layer_brick.types.contains(&"Anchor");
+        spawn_brick(commands, asset_server, position, brick_size, brick);
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct BoardBrick {
+    health: f32,
+    max_health: f32,
+    material: BrickMaterial,
+    source: BrickSource,
+    /// Grid cell the brick was spawned in
+    cell: IVec2,
+    /// Whether the brick is held in place regardless of the bricks around it
+    anchored: bool,
+}
+
+impl BoardBrick {
+    fn new(material: BrickMaterial, source: BrickSource, cell: IVec2) -> Self {
+        Self {
+            health: material.health,
+            max_health: material.health,
+            material,
+            source,
+            cell,
+            anchored: false,
+        }
+    }
+
+    pub fn source(&self) -> BrickSource {
+        self.source
+    }
+
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn max_health(&self) -> f32 {
+        self.max_health
+    }
+}
+
+/// Physics properties of a brick made of `material`.
+fn material_physics(material: &BrickMaterial) -> (ColliderMassProperties, Friction, Restitution) {
+    (
+        ColliderMassProperties::Density(material.density),
+        Friction::coefficient(material.friction),
+        Restitution::coefficient(material.restitution),
+    )
+}
+
+fn spawn_brick(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec2,
+    size: Vec2,
+    brick: BoardBrick,
+) {
+    let texture = brick
+        .material
+        .texture
+        .as_ref()
+        .map(|path| asset_server.load(path.clone()))
+        .unwrap_or_default();
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: brick.material.color,
+                custom_size: Some(size),
+                ..Default::default()
+            },
+            texture,
+            transform: Transform::from_xyz(position.x, position.y, 0.0),
+            ..default()
+        })
+        .insert(RigidBody::Fixed)
+        .insert(Collider::cuboid(size.x / 2.0, size.y / 2.0))
+        .insert(material_physics(&brick.material))
+        .insert(brick);
+}
+
+/// Damage dealt to a brick.
+#[derive(Event, Debug)]
+pub struct DamageEvent {
+    pub entity: Entity,
+    pub damage: f32,
+    /// Player whose shot did it
+    pub shooter: Option<Entity>,
+}
+
+fn read_colisions(
+    mut reader: EventReader<CollisionEvent>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    shot_quey: Query<&Shot>,
+    board_query: Query<&BoardBrick>,
+    falling_query: Query<&FallingBrick>,
+) {
+    for event in reader.read() {
+        trace!(?event, "Collision");
+        if let CollisionEvent::Started(collider1, collider2, _) = event {
+            // Collapsing bricks damage whatever they land on
+            for (falling, other) in [(collider1, collider2), (collider2, collider1)] {
+                if let (Ok(falling), Ok(_)) = (falling_query.get(*falling), board_query.get(*other))
+                {
+                    if let Some(damage) = falling.impact_damage() {
+                        damage_event_writer.send(DamageEvent {
+                            entity: *other,
+                            damage,
+                            shooter: None,
+                        });
+                    }
+                }
+            }
+
+            let damage = 50.0f32;
+
+            if let (Ok(shot), Ok(_)) = (shot_quey.get(*collider1), board_query.get(*collider2)) {
+                damage_event_writer.send(DamageEvent {
+                    entity: *collider2,
+                    damage,
+                    shooter: Some(shot.shooter),
+                });
+            } else {
+                if let (Ok(shot), Ok(_)) = (shot_quey.get(*collider2), board_query.get(*collider1))
+                {
+                    damage_event_writer.send(DamageEvent {
+                        entity: *collider1,
+                        damage,
+                        shooter: Some(shot.shooter),
+                    });
+                }
+            };
+        }
+    }
+}
+
+fn handle_health(
+    mut brick_query: Query<(Entity, &mut BoardBrick, &mut Sprite)>,
+    mut fracture_event_writer: EventWriter<FractureEvent>,
+    mut damage_event_reader: EventReader<DamageEvent>,
+) {
+    for event in damage_event_reader.read() {
+        if let Ok((entity, mut brick, mut sprite)) = brick_query.get_mut(event.entity) {
+            if brick.material.is_indestructible() {
+                continue;
+            }
+            brick.health -= brick.material.damage(event.damage);
+            if brick.health <= 0.0 {
+                fracture_event_writer.send(FractureEvent(entity));
+            } else {
+                let [r, g, b, a] = brick.material.color.as_rgba_f32();
+                let color_vec = Vec3::new(r, g, b).lerp(
+                    Vec3::new(1.0, 0.0, 0.0),
+                    1.0 - brick.health / brick.max_health,
+                );
+                sprite.color = Color::rgba(color_vec.x, color_vec.y, color_vec.z, a);
+            }
+        }
+    }
+}
+
+/// A brick that ran out of health and breaks apart.
+#[derive(Event, Debug)]
+pub struct FractureEvent(pub Entity);
+
+fn handle_fracture(
+    mut commands: Commands,
+    mut fracture_event_reader: EventReader<FractureEvent>,
+    mut support_writer: EventWriter<SupportRemoved>,
+    settings: Res<FractureSettings>,
+    query: Query<(
+        &Transform,
+        &Sprite,
+        &Handle<Image>,
+        &BoardBrick,
+        Has<FallingBrick>,
+    )>,
+) {
+    let mut fractured = HashSet::new();
+    // Read the events
+    for event in fracture_event_reader.read() {
+        let entity = event.0;
+        if !fractured.insert(entity) {
+            continue;
+        }
+        if let Ok((t, sprite, texture, brick, falling)) = query.get(entity) {
+            let _span = debug_span!("fracture", brick = ?entity).entered();
+            let original_size = sprite.custom_size.expect("Sprite must have custom size");
+
+            commands.entity(entity).despawn_recursive();
+            if !falling {
+                support_writer.send(SupportRemoved(entity));
+            }
+
+            let grid = brick.material.fracture.grid(original_size);
+            if grid == UVec2::ZERO {
+                continue;
+            }
+            let new_size = original_size / grid.as_vec2();
+            if new_size.min_element() < settings.min_fragment_size {
+                // Too small to split any further, crumble away
+                continue;
+            }
+
+            let health = brick.max_health * settings.fragment_health;
+            // Split into a grid of sprites
+            for y in 0..grid.y {
+                let delta_y = (new_size.y + 3.0) * (y as f32 - (grid.y - 1) as f32 / 2.0);
+                for x in 0..grid.x {
+                    let delta_x = (new_size.x + 3.0) * (x as f32 - (grid.x - 1) as f32 / 2.0);
+                    let mut transform = *t;
+                    transform.translation += Vec3::new(delta_x, delta_y, 0.0);
+
+                    trace!(from = ?t.translation, to = ?transform.translation, "Fragment");
+
+                    commands
+                        .spawn((SpriteBundle {
+                            transform,
+                            sprite: Sprite {
+                                color: brick.material.color,
+                                custom_size: Some(new_size),
+                                ..Default::default()
+                            },
+                            texture: texture.clone(),
+                            ..default()
+                        },))
+                        .insert(Collider::cuboid(new_size.x / 2.0, new_size.y / 2.0))
+                        .insert(material_physics(&brick.material))
+                        .insert(RigidBody::Dynamic)
+                        .insert(Velocity::default())
+                        .insert(Sleeping::default())
+                        .insert(ActiveEvents::COLLISION_EVENTS)
+                        .insert(BoardBrick {
+                            health,
+                            max_health: health,
+                            material: brick.material.clone(),
+                            ..*brick
+                        })
+                        .insert(FallingBrick::default())
+                        .insert(Debris::new(&settings));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_get_the_material_of_their_tag() {
+        let mut project = ldtk::load("assets/test.ldtk").expect("failed to load ldtk file");
+        let materials = BrickMaterials::default();
+        let layers = project.levels[0].layer_instances.as_mut().unwrap();
+        let index = layers
+            .iter()
+            .position(|layer| layer.layer_instance_type == "Tiles")
+            .unwrap();
+        layers[index].grid_tiles.truncate(5);
+        for (t, tile) in layers[index].grid_tiles.iter_mut().enumerate() {
+            tile.t = t as i64;
+        }
+
+        let layer = &project.levels[0].layer_instances.as_ref().unwrap()[index];
+        let bricks = layer_bricks(&project, index, layer);
+        let types: Vec<_> = bricks.iter().map(|brick| brick.types.clone()).collect();
+        assert_eq!(
+            types,
+            [
+                ["Destructable"],
+                ["Window"],
+                ["Steel"],
+                ["Indestructible"],
+                ["Anchor"]
+            ]
+        );
+        let steel = materials.get(bricks[2].types.iter().copied());
+        assert_eq!(steel.health, materials.materials["Steel"].health);
+        assert!(materials
+            .get(bricks[3].types.iter().copied())
+            .is_indestructible());
+        assert!(!materials
+            .get(bricks[0].types.iter().copied())
+            .is_indestructible());
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::{BoardBrick, CraterEvent, DamageEvent, FractureEvent};
+
+/// Look of the particles thrown off a brick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParticleKind {
+    /// Slow, grey-brown puffs that settle
+    Dust,
+    /// Fast, bright streaks that fall quickly
+    Sparks,
+    /// Large, dark clouds that drift upwards
+    Smoke,
+}
+
+/// How many particles an emitter spawns and how they move.
+#[derive(Debug, Clone)]
+pub struct EmitterPreset {
+    /// Particles per burst
+    pub count: usize,
+    /// Pixels per second, each particle picks a speed up to this
+    pub speed: f32,
+    /// Seconds a particle lives, each picks a lifetime between half and all of it
+    pub lifetime: f32,
+    /// Side of a particle in pixels
+    pub size: f32,
+    /// Particles pick a color between these two
+    pub colors: [Color; 2],
+    /// Pixels per second squared, negative pulls down
+    pub gravity: f32,
+    /// Fraction of the speed lost per second
+    pub drag: f32,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ParticleSettings {
+    pub presets: HashMap<ParticleKind, EmitterPreset>,
+    /// Most particles alive at once, bursts beyond it are cut short
+    pub max_particles: usize,
+    /// Particles on a fracture compared to a hit
+    pub fracture_scale: f32,
+    /// Damage of a hit that throws a full burst, lighter hits throw fewer particles
+    pub full_burst_damage: f32,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        let presets = [
+            (
+                ParticleKind::Dust,
+                EmitterPreset {
+                    count: 8,
+                    speed: 30.0,
+                    lifetime: 1.0,
+                    size: 2.0,
+                    colors: [Color::rgb(0.6, 0.55, 0.45), Color::rgb(0.8, 0.75, 0.65)],
+                    gravity: -20.0,
+                    drag: 2.0,
+                },
+            ),
+            (
+                ParticleKind::Sparks,
+                EmitterPreset {
+                    count: 6,
+                    speed: 80.0,
+                    lifetime: 0.4,
+                    size: 1.0,
+                    colors: [Color::rgb(1.0, 0.9, 0.3), Color::rgb(1.0, 0.5, 0.1)],
+                    gravity: -150.0,
+                    drag: 0.5,
+                },
+            ),
+            (
+                ParticleKind::Smoke,
+                EmitterPreset {
+                    count: 6,
+                    speed: 15.0,
+                    lifetime: 2.0,
+                    size: 5.0,
+                    colors: [Color::rgb(0.3, 0.3, 0.3), Color::rgb(0.5, 0.5, 0.5)],
+                    gravity: 10.0,
+                    drag: 1.0,
+                },
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        Self {
+            presets,
+            max_particles: 500,
+            fracture_scale: 3.0,
+            full_burst_damage: 50.0,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    drag: f32,
+    lifetime: Timer,
+    /// Alpha the particle starts fading from
+    alpha: f32,
+}
+
+/// Particles that fit in the budget when `alive` already exist.
+fn budgeted(count: usize, alive: usize, max_particles: usize) -> usize {
+    count.min(max_particles.saturating_sub(alive))
+}
+
+/// Throws dust, sparks and smoke off damaged and fractured bricks, and smoke out of craters.
+pub fn emit_particles(
+    mut commands: Commands,
+    settings: Res<ParticleSettings>,
+    mut damage_reader: EventReader<DamageEvent>,
+    mut fracture_reader: EventReader<FractureEvent>,
+    mut crater_reader: EventReader<CraterEvent>,
+    brick_query: Query<(&GlobalTransform, &BoardBrick)>,
+    particle_query: Query<(), With<Particle>>,
+) {
+    let mut bursts = Vec::new();
+    for event in damage_reader.read() {
+        if let Ok((transform, brick)) = brick_query.get(event.entity) {
+            let scale = (event.damage / settings.full_burst_damage).clamp(0.0, 1.0);
+            bursts.push((
+                transform.translation().truncate(),
+                brick.material.particles,
+                scale,
+            ));
+        }
+    }
+    for event in fracture_reader.read() {
+        if let Ok((transform, brick)) = brick_query.get(event.0) {
+            let position = transform.translation().truncate();
+            bursts.push((position, brick.material.particles, settings.fracture_scale));
+            bursts.push((position, ParticleKind::Smoke, 1.0));
+        }
+    }
+    for event in crater_reader.read() {
+        let scale = event.radius / 4.0;
+        bursts.push((event.position, ParticleKind::Smoke, scale));
+        bursts.push((event.position, ParticleKind::Dust, scale));
+    }
+
+    let mut alive = particle_query.iter().len();
+    let mut rng = rand::thread_rng();
+    for (position, kind, scale) in bursts {
+        let Some(preset) = settings.presets.get(&kind) else {
+            continue;
+        };
+        let count = (preset.count as f32 * scale).round() as usize;
+        let count = budgeted(count, alive, settings.max_particles);
+        alive += count;
+
+        for _ in 0..count {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let velocity = Vec2::from_angle(angle) * rng.gen_range(0.0..=preset.speed);
+            let lifetime = rng.gen_range(preset.lifetime / 2.0..=preset.lifetime);
+            let color = preset.colors[0].as_rgba_f32();
+            let other = preset.colors[1].as_rgba_f32();
+            let t = rng.gen::<f32>();
+            let mix = |i: usize| color[i] + (other[i] - color[i]) * t;
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(mix(0), mix(1), mix(2), mix(3)),
+                        custom_size: Some(Vec2::splat(preset.size)),
+                        ..default()
+                    },
+                    // In front of the bricks
+                    transform: Transform::from_xyz(position.x, position.y, 1.0),
+                    ..default()
+                },
+                Particle {
+                    velocity,
+                    gravity: preset.gravity,
+                    drag: preset.drag,
+                    lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+                    alpha: mix(3),
+                },
+            ));
+        }
+    }
+}
+
+/// Moves particles and fades them out over their lifetime.
+pub fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite) in query.iter_mut() {
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let drag = (1.0 - particle.drag * dt).max(0.0);
+        particle.velocity.y += particle.gravity * dt;
+        particle.velocity *= drag;
+        transform.translation += (particle.velocity * dt).extend(0.0);
+        sprite
+            .color
+            .set_a(particle.alpha * particle.lifetime.percent_left());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_stop_at_the_budget() {
+        assert_eq!(budgeted(10, 0, 100), 10);
+        assert_eq!(budgeted(10, 95, 100), 5);
+        assert_eq!(budgeted(10, 120, 100), 0);
+    }
+}
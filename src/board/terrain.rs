@@ -0,0 +1,421 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+use bevy_rapier2d::{dynamics::RigidBody, geometry::Collider, pipeline::CollisionEvent};
+
+use super::{layer_bricks, BrickMaterials};
+use crate::{ldtk, player::Shot};
+
+/// Settings for the pixel terrain mode, where the level is a destructible mask rather than
+/// a set of bricks.
+#[derive(Resource, Debug, Clone)]
+pub struct TerrainSettings {
+    /// Radius of the crater carved by a shot, in pixels
+    pub crater_radius: f32,
+    /// Side of the square regions whose colliders are rebuilt independently, in pixels
+    pub chunk_size: i32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            crater_radius: 12.0,
+            chunk_size: 32,
+        }
+    }
+}
+
+/// A shot exploded and carved a crater in the terrain.
+#[derive(Event, Debug)]
+pub struct CraterEvent {
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+/// Which pixels of the level are solid, with `(0, 0)` the top left pixel as in LDtk.
+#[derive(Debug, Clone)]
+pub struct TerrainMask {
+    width: i32,
+    height: i32,
+    solid: Vec<bool>,
+    /// Solid pixels that craters cannot carve
+    indestructible: Vec<bool>,
+}
+
+impl TerrainMask {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            solid: vec![false; (width * height) as usize],
+            indestructible: vec![false; (width * height) as usize],
+        }
+    }
+
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height && self.solid[self.index(x, y)]
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (x + y * self.width) as usize
+    }
+
+    pub fn fill(&mut self, min: IVec2, max: IVec2, indestructible: bool) {
+        let min = min.max(IVec2::ZERO);
+        let max = max.min(IVec2::new(self.width, self.height));
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let index = self.index(x, y);
+                self.solid[index] = true;
+                self.indestructible[index] = indestructible;
+            }
+        }
+    }
+
+    /// Clears the destructible pixels whose centers lie within `radius` of `center`, and
+    /// returns them.
+    pub fn carve(&mut self, center: Vec2, radius: f32) -> Vec<IVec2> {
+        let min = (center - radius).floor().as_ivec2().max(IVec2::ZERO);
+        let max = (center + radius)
+            .ceil()
+            .as_ivec2()
+            .min(IVec2::new(self.width, self.height));
+        let mut carved = Vec::new();
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let pixel_center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let index = self.index(x, y);
+                if self.solid[index]
+                    && !self.indestructible[index]
+                    && pixel_center.distance(center) <= radius
+                {
+                    self.solid[index] = false;
+                    carved.push(IVec2::new(x, y));
+                }
+            }
+        }
+        carved
+    }
+
+    /// Number of marching squares cells along each axis. Cell `(i, j)` has the pixel
+    /// `(i - 1, j - 1)` as its top left corner, so the outline closes along the level border.
+    fn cells(&self) -> IVec2 {
+        IVec2::new(self.width + 1, self.height + 1)
+    }
+
+    /// Traces the outline of the solid pixels with marching squares over the cells in
+    /// `[min, max)`. Segment end points are in pixel coordinates.
+    pub fn contour(&self, min: IVec2, max: IVec2) -> Vec<[Vec2; 2]> {
+        let min = min.max(IVec2::ZERO);
+        let max = max.min(self.cells());
+        let mut segments = Vec::new();
+        for j in min.y..max.y {
+            for i in min.x..max.x {
+                let (x, y) = (i - 1, j - 1);
+                let case = (self.is_solid(x, y) as u8) << 3
+                    | (self.is_solid(x + 1, y) as u8) << 2
+                    | (self.is_solid(x + 1, y + 1) as u8) << 1
+                    | self.is_solid(x, y + 1) as u8;
+
+                // Edge midpoints between the centers of the corner pixels
+                let origin = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let top = origin + Vec2::new(0.5, 0.0);
+                let right = origin + Vec2::new(1.0, 0.5);
+                let bottom = origin + Vec2::new(0.5, 1.0);
+                let left = origin + Vec2::new(0.0, 0.5);
+
+                match case {
+                    1 | 14 => segments.push([left, bottom]),
+                    2 | 13 => segments.push([bottom, right]),
+                    3 | 12 => segments.push([left, right]),
+                    4 | 11 => segments.push([top, right]),
+                    6 | 9 => segments.push([top, bottom]),
+                    7 | 8 => segments.push([left, top]),
+                    5 => {
+                        segments.push([left, top]);
+                        segments.push([bottom, right]);
+                    }
+                    10 => {
+                        segments.push([top, right]);
+                        segments.push([left, bottom]);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        segments
+    }
+}
+
+/// The destructible terrain of the current level.
+#[derive(Resource, Debug)]
+pub struct Terrain {
+    mask: TerrainMask,
+    image: Handle<Image>,
+    level_size: Vec2,
+    chunk_size: i32,
+    /// Collider entity of each chunk of marching squares cells
+    chunks: HashMap<IVec2, Entity>,
+    /// Chunks whose collider must be rebuilt
+    dirty: HashSet<IVec2>,
+}
+
+impl Terrain {
+    fn to_pixel(&self, position: Vec2) -> Vec2 {
+        Vec2::new(
+            position.x + self.level_size.x / 2.0,
+            self.level_size.y / 2.0 - position.y,
+        )
+    }
+
+    fn to_world(&self, pixel: Vec2) -> Vec2 {
+        Vec2::new(
+            pixel.x - self.level_size.x / 2.0,
+            self.level_size.y / 2.0 - pixel.y,
+        )
+    }
+
+    fn chunk_collider(&self, chunk: IVec2) -> Option<Collider> {
+        let min = chunk * self.chunk_size;
+        let segments = self.mask.contour(min, min + self.chunk_size);
+        if segments.is_empty() {
+            return None;
+        }
+
+        // Share the end points between neighbouring segments
+        let mut vertices = Vec::new();
+        let mut vertex_indices = HashMap::new();
+        let mut indices = Vec::new();
+        for segment in segments {
+            let mut index = |point: Vec2| {
+                *vertex_indices
+                    .entry(point.to_array().map(f32::to_bits))
+                    .or_insert_with(|| {
+                        vertices.push(self.to_world(point));
+                        vertices.len() as u32 - 1
+                    })
+            };
+            indices.push([index(segment[0]), index(segment[1])]);
+        }
+        Some(Collider::polyline(vertices, Some(indices)))
+    }
+}
+
+/// One collider of the terrain.
+#[derive(Component, Debug)]
+pub struct TerrainChunk;
+
+/// The sprite showing the terrain.
+#[derive(Component, Debug)]
+pub struct TerrainSprite;
+
+/// Rasterizes the tile and IntGrid layers of a level into a terrain mask and texture.
+pub fn spawn_terrain(
+    commands: &mut Commands,
+    images: &mut Assets<Image>,
+    materials: &BrickMaterials,
+    project: &ldtk::Project,
+    level: &ldtk::Level,
+    settings: &TerrainSettings,
+) {
+    let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+    let width = level_size.x as i32;
+    let height = level_size.y as i32;
+    let mut mask = TerrainMask::new(width, height);
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+
+    let layers = level.layer_instances.iter().flatten();
+    for (index, layer) in layers.enumerate() {
+        for brick in layer_bricks(project, index, layer) {
+            let material = materials.get(brick.types.iter().copied());
+            let color = material.color.as_rgba_u8();
+
+            let min = IVec2::new(brick.px[0] as i32, brick.px[1] as i32);
+            let max = min + layer.grid_size as i32;
+            mask.fill(min, max, material.is_indestructible());
+            for y in min.y.max(0)..max.y.min(height) {
+                for x in min.x.max(0)..max.x.min(width) {
+                    let index = ((x + y * width) * 4) as usize;
+                    pixels[index..index + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixels,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::nearest();
+    let image = images.add(image);
+
+    commands.spawn((
+        SpriteBundle {
+            texture: image.clone(),
+            sprite: Sprite {
+                custom_size: Some(level_size),
+                ..Default::default()
+            },
+            ..default()
+        },
+        TerrainSprite,
+    ));
+
+    let chunk_count = (mask.cells() + settings.chunk_size - 1) / settings.chunk_size;
+    let mut terrain = Terrain {
+        mask,
+        image,
+        level_size,
+        chunk_size: settings.chunk_size,
+        chunks: HashMap::new(),
+        dirty: HashSet::new(),
+    };
+    for y in 0..chunk_count.y {
+        for x in 0..chunk_count.x {
+            let chunk = IVec2::new(x, y);
+            let entity = commands
+                .spawn((TransformBundle::default(), RigidBody::Fixed, TerrainChunk))
+                .id();
+            terrain.chunks.insert(chunk, entity);
+            terrain.dirty.insert(chunk);
+        }
+    }
+    commands.insert_resource(terrain);
+}
+
+pub fn read_crater_collisions(
+    mut commands: Commands,
+    mut reader: EventReader<CollisionEvent>,
+    mut crater_event_writer: EventWriter<CraterEvent>,
+    settings: Res<TerrainSettings>,
+    shot_query: Query<&Transform, With<Shot>>,
+    chunk_query: Query<&TerrainChunk>,
+) {
+    let mut exploded = HashSet::new();
+    for event in reader.read() {
+        if let CollisionEvent::Started(collider1, collider2, _) = event {
+            for (shot, other) in [(collider1, collider2), (collider2, collider1)] {
+                if let (Ok(transform), Ok(_)) = (shot_query.get(*shot), chunk_query.get(*other)) {
+                    if exploded.insert(*shot) {
+                        crater_event_writer.send(CraterEvent {
+                            position: transform.translation.truncate(),
+                            radius: settings.crater_radius,
+                        });
+                        commands.entity(*shot).despawn_recursive();
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn carve_craters(
+    mut crater_event_reader: EventReader<CraterEvent>,
+    mut terrain: ResMut<Terrain>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for event in crater_event_reader.read() {
+        let center = terrain.to_pixel(event.position);
+        let carved = terrain.mask.carve(center, event.radius);
+        if carved.is_empty() {
+            continue;
+        }
+
+        if let Some(image) = images.get_mut(&terrain.image) {
+            for pixel in &carved {
+                let index = ((pixel.x + pixel.y * terrain.mask.width) * 4) as usize;
+                image.data[index + 3] = 0;
+            }
+        }
+
+        // A pixel is a corner of the cells (x, y) to (x + 1, y + 1)
+        let chunk_size = terrain.chunk_size;
+        for pixel in carved {
+            for offset in [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE] {
+                terrain.dirty.insert((pixel + offset) / chunk_size);
+            }
+        }
+    }
+}
+
+/// Rebuilds the colliders of the chunks touched by craters.
+pub fn rebuild_dirty_chunks(mut commands: Commands, mut terrain: ResMut<Terrain>) {
+    if terrain.dirty.is_empty() {
+        return;
+    }
+
+    let dirty: Vec<_> = terrain.dirty.drain().collect();
+    for chunk in dirty {
+        let Some(&entity) = terrain.chunks.get(&chunk) else {
+            continue;
+        };
+        match terrain.chunk_collider(chunk) {
+            Some(collider) => commands.entity(entity).insert(collider),
+            None => commands.entity(entity).remove::<Collider>(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(mut segments: Vec<[Vec2; 2]>) -> Vec<[(f32, f32); 2]> {
+        let mut segments: Vec<_> = segments
+            .drain(..)
+            .map(|segment| {
+                let mut points = segment.map(|p| (p.x, p.y));
+                points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                points
+            })
+            .collect();
+        segments.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        segments
+    }
+
+    #[test]
+    fn single_pixel_outline() {
+        let mut mask = TerrainMask::new(3, 3);
+        mask.fill(IVec2::new(1, 1), IVec2::new(2, 2), false);
+
+        let segments = mask.contour(IVec2::ZERO, mask.cells());
+        assert_eq!(segments.len(), 4);
+        // A diamond around the pixel center
+        for segment in segments {
+            for point in segment {
+                assert_eq!(point.distance(Vec2::new(1.5, 1.5)), 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn chunks_trace_the_whole_outline() {
+        let mut mask = TerrainMask::new(20, 12);
+        mask.fill(IVec2::new(2, 4), IVec2::new(18, 12), false);
+        mask.carve(Vec2::new(9.0, 5.0), 4.0);
+
+        let whole = mask.contour(IVec2::ZERO, mask.cells());
+        let chunk_size = 8;
+        let mut chunked = Vec::new();
+        for y in (0..mask.cells().y).step_by(chunk_size) {
+            for x in (0..mask.cells().x).step_by(chunk_size) {
+                let min = IVec2::new(x, y);
+                chunked.extend(mask.contour(min, min + chunk_size as i32));
+            }
+        }
+        assert!(!whole.is_empty());
+        assert_eq!(normalized(whole), normalized(chunked));
+    }
+}
@@ -0,0 +1,262 @@
+use bevy::prelude::*;
+use bevy_rapier2d::plugin::{RapierConfiguration, TimestepMode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{CraterEvent, FractureEvent},
+    config::{env_value, Config},
+    team::PlayerEliminated,
+};
+
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Shake the camera on big impacts
+    pub shake: bool,
+    /// Slow down physics for a moment when a gorilla is knocked out
+    pub hit_stop: bool,
+    /// Offset in pixels at full trauma
+    pub max_offset: f32,
+    /// Rotation in radians at full trauma
+    pub max_angle: f32,
+    /// Trauma lost per second
+    pub trauma_decay: f32,
+    /// Trauma per pixel of crater radius
+    pub crater_trauma: f32,
+    /// Trauma per pixel of side of a breaking brick, so fragments shake less than bricks
+    pub fracture_trauma: f32,
+    pub knockout_trauma: f32,
+    /// Impacts further than this from the camera do not shake it, closer ones shake it more
+    pub shake_range: f32,
+    /// Real seconds physics stays slowed down
+    pub hit_stop_time: f32,
+    pub hit_stop_scale: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            shake: true,
+            hit_stop: true,
+            max_offset: 8.0,
+            max_angle: 0.05,
+            trauma_decay: 1.5,
+            crater_trauma: 0.05,
+            fracture_trauma: 0.01,
+            knockout_trauma: 0.8,
+            shake_range: 400.0,
+            hit_stop_time: 0.4,
+            hit_stop_scale: 0.2,
+        }
+    }
+}
+
+impl CameraSettings {
+    /// Without shake and hit-stop.
+    pub fn reduced_motion(&self) -> bool {
+        !self.shake && !self.hit_stop
+    }
+
+    pub fn set_reduced_motion(&mut self, reduced: bool) {
+        self.shake = !reduced;
+        self.hit_stop = !reduced;
+    }
+
+    /// Shake and hit-stop turned off when `GORILLAS_REDUCED_MOTION` is `true`.
+    pub fn apply_env(&mut self) {
+        if env_value("GORILLAS_REDUCED_MOTION") == Some(true) {
+            self.set_reduced_motion(true);
+        }
+    }
+}
+
+/// Shakes the camera it is on, trauma between `0.0` and `1.0` decays over time.
+#[derive(Component, Debug, Default)]
+pub struct Shake {
+    trauma: f32,
+    /// Offset applied last frame, undone before the next one
+    offset: Vec2,
+    angle: f32,
+}
+
+impl Shake {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+}
+
+/// Trauma of an impact of `magnitude` at `distance` from the camera.
+fn trauma_at(magnitude: f32, distance: f32, range: f32) -> f32 {
+    magnitude * (1.0 - distance / range).clamp(0.0, 1.0)
+}
+
+/// Time left of a hit-stop and the time scale to go back to.
+#[derive(Resource, Debug)]
+struct HitStop {
+    timer: Timer,
+    time_scale: f32,
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = app
+            .world
+            .get_resource::<Config>()
+            .map(|config| config.camera.clone())
+            .unwrap_or_default();
+        app.insert_resource(settings).add_systems(
+            Update,
+            (
+                add_shake,
+                (add_trauma, shake_camera).chain(),
+                (start_hit_stop, end_hit_stop).chain(),
+            ),
+        );
+    }
+}
+
+fn add_shake(mut commands: Commands, camera_query: Query<Entity, (With<Camera>, Without<Shake>)>) {
+    for camera in camera_query.iter() {
+        commands.entity(camera).insert(Shake::default());
+    }
+}
+
+fn add_trauma(
+    settings: Res<CameraSettings>,
+    mut crater_reader: EventReader<CraterEvent>,
+    mut fracture_reader: EventReader<FractureEvent>,
+    mut eliminated_reader: EventReader<PlayerEliminated>,
+    brick_query: Query<(&GlobalTransform, &Sprite)>,
+    mut camera_query: Query<(&GlobalTransform, &mut Shake)>,
+) {
+    let mut impacts = Vec::new();
+    for event in crater_reader.read() {
+        impacts.push((Some(event.position), event.radius * settings.crater_trauma));
+    }
+    for event in fracture_reader.read() {
+        if let Ok((transform, sprite)) = brick_query.get(event.0) {
+            let position = transform.translation().truncate();
+            let size = sprite.custom_size.unwrap_or_default();
+            let side = (size.x * size.y).sqrt();
+            impacts.push((Some(position), side * settings.fracture_trauma));
+        }
+    }
+    // Knockouts shake the whole screen wherever they happen
+    for _ in eliminated_reader.read() {
+        impacts.push((None, settings.knockout_trauma));
+    }
+    if !settings.shake {
+        return;
+    }
+
+    for (camera_transform, mut shake) in camera_query.iter_mut() {
+        // Where the camera would be without shaking
+        let camera = camera_transform.translation().truncate() - shake.offset;
+        for (position, magnitude) in &impacts {
+            let trauma = match position {
+                Some(position) => {
+                    trauma_at(*magnitude, position.distance(camera), settings.shake_range)
+                }
+                None => *magnitude,
+            };
+            shake.add_trauma(trauma);
+        }
+    }
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
+    mut camera_query: Query<(&mut Transform, &mut Shake)>,
+) {
+    let t = time.elapsed_seconds();
+    for (mut transform, mut shake) in camera_query.iter_mut() {
+        transform.translation -= shake.offset.extend(0.0);
+        transform.rotate_z(-shake.angle);
+
+        shake.trauma = (shake.trauma - settings.trauma_decay * time.delta_seconds()).max(0.0);
+        if !settings.shake {
+            shake.trauma = 0.0;
+        }
+        // Squared, so small knocks barely move the camera
+        let amount = shake.trauma * shake.trauma;
+        // Sines of unrelated frequencies as a cheap stand-in for noise
+        shake.offset = settings.max_offset
+            * amount
+            * Vec2::new(
+                (t * 37.0).sin() * (t * 13.0).cos(),
+                (t * 41.0).sin() * (t * 7.0).cos(),
+            );
+        shake.angle = settings.max_angle * amount * (t * 29.0).sin();
+
+        transform.translation += shake.offset.extend(0.0);
+        transform.rotate_z(shake.angle);
+    }
+}
+
+fn time_scale(mode: &mut TimestepMode) -> Option<&mut f32> {
+    match mode {
+        TimestepMode::Variable { time_scale, .. }
+        | TimestepMode::Interpolated { time_scale, .. } => Some(time_scale),
+        TimestepMode::Fixed { .. } => None,
+    }
+}
+
+fn start_hit_stop(
+    mut commands: Commands,
+    settings: Res<CameraSettings>,
+    mut eliminated_reader: EventReader<PlayerEliminated>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    hit_stop: Option<Res<HitStop>>,
+) {
+    // Falls knock gorillas out too, only shots stop time
+    let lethal_hit = eliminated_reader
+        .read()
+        .filter(|event| event.shooter.is_some())
+        .count()
+        > 0;
+    if !lethal_hit || !settings.hit_stop || hit_stop.is_some() {
+        return;
+    }
+    let Some(scale) = time_scale(&mut rapier_config.timestep_mode) else {
+        return;
+    };
+
+    commands.insert_resource(HitStop {
+        timer: Timer::from_seconds(settings.hit_stop_time, TimerMode::Once),
+        time_scale: *scale,
+    });
+    *scale *= settings.hit_stop_scale;
+}
+
+fn end_hit_stop(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    hit_stop: Option<ResMut<HitStop>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    let Some(mut hit_stop) = hit_stop else {
+        return;
+    };
+    if !hit_stop.timer.tick(time.delta()).finished() {
+        return;
+    }
+    if let Some(scale) = time_scale(&mut rapier_config.timestep_mode) {
+        *scale = hit_stop.time_scale;
+    }
+    commands.remove_resource::<HitStop>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_impacts_shake_less() {
+        assert_eq!(trauma_at(0.5, 0.0, 100.0), 0.5);
+        assert_eq!(trauma_at(0.5, 50.0, 100.0), 0.25);
+        assert_eq!(trauma_at(0.5, 200.0, 100.0), 0.0);
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use bevy::{app::AppExit, asset::LoadState, prelude::*};
+use clap::Parser;
+
+use crate::{
+    ldtk::LdtkAsset,
+    menu::{AppState, Controller, LevelChoice, MatchSettings, WeaponSet},
+    player::level_players,
+    skyline::{self, SkylineSettings},
+};
+
+/// Gorillas, with physics. Giving a level or seed skips the menu.
+#[derive(Parser, Debug, Clone, Default)]
+#[command(version, about)]
+pub struct Cli {
+    /// LDtk project to play, relative to the assets folder
+    #[arg(long)]
+    pub level: Option<String>,
+    /// Identifier of the level in the project, the first level when not given
+    #[arg(long, requires = "level")]
+    pub level_id: Option<String>,
+    /// Seed of a random skyline to play, when no level is given
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Run without a window, sound or input. Every gorilla is played by the computer, or
+    /// by the replay, and the game exits when the match is over.
+    #[arg(long)]
+    pub headless: bool,
+    /// Let another program play the gorillas not played by the computer: each of their
+    /// turns writes an observation to stdout as a line of JSON and waits for an action line
+    /// on stdin. Implies `--headless`.
+    #[arg(long)]
+    pub gym: bool,
+    /// Number of gorillas played by the computer, counted from the last one
+    #[arg(long, default_value_t = 0)]
+    pub ai: usize,
+    /// Number of rounds to play
+    #[arg(long, default_value_t = 1)]
+    pub rounds: u32,
+    /// Weapons the players get
+    #[arg(long, value_enum, default_value_t = WeaponSet::Full)]
+    pub weapons: WeaponSet,
+    /// Blow wind across the board
+    #[arg(long)]
+    pub wind: bool,
+    /// Play on pixel terrain with craters instead of bricks
+    #[arg(long)]
+    pub terrain: bool,
+    /// Replay file to play the shots of back, on its level unless one is given
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+    /// File to record the shots of the game to, for `--replay`
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+    /// File to write the statistics of the match to when it is over, as CSV when it ends
+    /// in `.csv` and JSON otherwise
+    #[arg(long)]
+    pub stats: Option<PathBuf>,
+    /// Config file to use instead of `GORILLAS_CONFIG` or `config.json`
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Size of the window, as WIDTHxHEIGHT
+    #[arg(long, value_parser = parse_size)]
+    pub window_size: Option<(f32, f32)>,
+    /// Log filter, like `debug` or `gorillas::board=trace,info`. Defaults to `RUST_LOG`.
+    #[arg(long)]
+    pub log: Option<String>,
+    /// File to also write the logs to, as JSON lines
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+}
+
+fn parse_size(s: &str) -> Result<(f32, f32), String> {
+    let (width, height) = s
+        .split_once('x')
+        .ok_or_else(|| format!("Expected WIDTHxHEIGHT, got {}", s))?;
+    let parse = |value: &str| {
+        value
+            .parse::<f32>()
+            .ok()
+            .filter(|value| *value > 0.0)
+            .ok_or_else(|| format!("Invalid size: {}", s))
+    };
+    Ok((parse(width)?, parse(height)?))
+}
+
+impl Cli {
+    /// Whether the menu is skipped.
+    pub fn skips_menu(&self) -> bool {
+        self.level.is_some() || self.seed.is_some() || self.headless || self.gym
+    }
+}
+
+/// Match to start once its level is loaded, skipping the menu.
+#[derive(Resource, Debug)]
+struct AutoStart {
+    level: Option<String>,
+    level_id: Option<String>,
+    seed: Option<u64>,
+    /// Computer players, counted from the last
+    ai: usize,
+    rounds: u32,
+    weapons: WeaponSet,
+    wind: bool,
+    terrain: bool,
+    headless: bool,
+    gym: bool,
+    map: Option<Handle<LdtkAsset>>,
+}
+
+/// Set when the match described on the command line could not be started, so the game
+/// can exit with an error once the app has stopped.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct StartFailed(Arc<AtomicBool>);
+
+impl StartFailed {
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Starts the match described on the command line.
+pub struct CliPlugin(pub Cli);
+
+impl Plugin for CliPlugin {
+    fn build(&self, app: &mut App) {
+        let cli = &self.0;
+        if !cli.skips_menu() {
+            return;
+        }
+        app.insert_resource(AutoStart {
+            level: cli.level.clone(),
+            level_id: cli.level_id.clone(),
+            seed: cli.seed,
+            ai: cli.ai,
+            rounds: cli.rounds,
+            weapons: cli.weapons,
+            wind: cli.wind,
+            terrain: cli.terrain,
+            headless: cli.headless || cli.gym,
+            gym: cli.gym,
+            map: None,
+        })
+        .add_systems(
+            Update,
+            auto_start
+                .run_if(in_state(AppState::Menu))
+                .run_if(resource_exists::<AutoStart>()),
+        );
+    }
+}
+
+fn auto_start(
+    mut commands: Commands,
+    mut auto_start: ResMut<AutoStart>,
+    asset_server: Res<AssetServer>,
+    mut maps: ResMut<Assets<LdtkAsset>>,
+    mut settings: ResMut<MatchSettings>,
+    mut next_state: ResMut<NextState<AppState>>,
+    failed: Res<StartFailed>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let map = match &auto_start.map {
+        Some(map) => map.clone(),
+        None => {
+            let map = match (&auto_start.level, auto_start.seed) {
+                (Some(level), _) => asset_server.load(level.clone()),
+                (None, Some(seed)) => maps.add(LdtkAsset {
+                    project: skyline::generate(&SkylineSettings::with_seed(seed)),
+                }),
+                (None, None) => settings.level.map.clone(),
+            };
+            auto_start.map = Some(map.clone());
+            map
+        }
+    };
+    if asset_server.get_load_state(&map) == Some(LoadState::Failed) {
+        error!(level = ?auto_start.level, "Failed to load the level");
+        commands.remove_resource::<AutoStart>();
+        failed.0.store(true, Ordering::Relaxed);
+        exit.send(AppExit);
+        return;
+    }
+    let Some(project) = maps.get(&map).map(|map| &map.project) else {
+        return;
+    };
+
+    let level = match &auto_start.level_id {
+        Some(id) => match project
+            .levels
+            .iter()
+            .position(|level| level.identifier == *id)
+        {
+            Some(level) => level,
+            None => {
+                warn!(id, "No such level in the project, playing the first one");
+                0
+            }
+        },
+        None => 0,
+    };
+    let Some(ldtk_level) = project.levels.get(level) else {
+        error!("The project has no levels");
+        commands.remove_resource::<AutoStart>();
+        return;
+    };
+
+    let players = level_players(ldtk_level).count();
+    // Nobody is at the keyboard of a headless game, unless a program plays instead
+    let others = if auto_start.headless && !auto_start.gym {
+        0
+    } else {
+        players.saturating_sub(auto_start.ai)
+    };
+    let other = if auto_start.gym {
+        Controller::External
+    } else {
+        Controller::Human
+    };
+    settings.slots = (0..players)
+        .map(|slot| {
+            if slot < others {
+                other
+            } else {
+                Controller::Computer
+            }
+        })
+        .collect();
+    settings.level = LevelChoice {
+        name: auto_start
+            .level
+            .clone()
+            .or_else(|| {
+                auto_start
+                    .seed
+                    .map(|seed| format!("Random skyline ({})", seed))
+            })
+            .unwrap_or_else(|| settings.level.name.clone()),
+        map,
+        level,
+        seed: match auto_start.level {
+            Some(_) => None,
+            None => auto_start.seed.or(settings.level.seed),
+        },
+    };
+    settings.rounds = auto_start.rounds.max(1);
+    settings.weapons = auto_start.weapons;
+    settings.wind = auto_start.wind;
+    settings.terrain = auto_start.terrain;
+    settings.unattended = auto_start.headless;
+
+    commands.remove_resource::<AutoStart>();
+    next_state.set(AppState::Playing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_sizes_are_parsed() {
+        assert_eq!(parse_size("1280x720"), Ok((1280.0, 720.0)));
+        assert!(parse_size("1280").is_err());
+        assert!(parse_size("0x720").is_err());
+    }
+}
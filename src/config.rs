@@ -0,0 +1,88 @@
+use std::{io::BufReader, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::AudioConfig, camera::CameraSettings, player::ChargeSettings, team::TeamSettings,
+    turn::TurnSettings,
+};
+
+/// Default location of the config file, relative to the working directory
+const CONFIG_PATH: &str = "config.json";
+
+/// Settings read from a JSON file at startup, see `config.example.json`. Missing fields keep
+/// their defaults, and the `GORILLAS_*` environment variables override the file.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub audio: AudioConfig,
+    pub turn: TurnSettings,
+    pub charge: ChargeSettings,
+    pub team: TeamSettings,
+    pub camera: CameraSettings,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Loads the file named by `GORILLAS_CONFIG`, or `config.json` when it exists.
+    pub fn from_env() -> Self {
+        let path = match std::env::var("GORILLAS_CONFIG") {
+            Ok(path) => path,
+            Err(_) if Path::new(CONFIG_PATH).exists() => CONFIG_PATH.to_string(),
+            Err(_) => return Self::default(),
+        };
+        Self::load(&path).unwrap_or_else(|err| {
+            warn!(path, %err, "Ignoring config");
+            Self::default()
+        })
+    }
+
+    /// Overrides the settings with the `GORILLAS_*` environment variables that are set.
+    pub fn apply_env(&mut self) {
+        self.turn.apply_env();
+        self.charge.apply_env();
+        self.team.apply_env();
+        self.camera.apply_env();
+    }
+}
+
+/// Parses an environment variable, ignoring it with a warning when it is malformed.
+pub fn env_value<T: std::str::FromStr>(key: &str) -> Option<T>
+where
+    T::Err: std::fmt::Debug,
+{
+    let value = std::env::var(key).ok()?;
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warn!(key, value, ?err, "Ignoring malformed environment variable");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{player::ChargeCurve, team::FriendlyFire, turn::TurnExpiry};
+
+    #[test]
+    fn example_config_loads() {
+        let config = Config::load("config.example.json").expect("failed to load example config");
+        assert_eq!(config.turn.on_expiry, TurnExpiry::AutoFire);
+        assert_eq!(config.charge.curve, ChargeCurve::EaseIn);
+        assert_eq!(config.team.friendly_fire, FriendlyFire::Reduced);
+        assert!(!config.camera.reduced_motion());
+
+        // Missing sections and fields keep their defaults
+        let config: Config = serde_json::from_str(r#"{"turn": {"time_limit": 5}}"#).unwrap();
+        assert_eq!(config.turn.time_limit, 5.0);
+        assert_eq!(config.turn.on_expiry, TurnExpiry::AutoFire);
+        assert_eq!(config.charge.max_force, ChargeSettings::default().max_force);
+    }
+}
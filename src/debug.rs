@@ -0,0 +1,200 @@
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy_rapier2d::prelude::*;
+
+use crate::{board::BoardBrick, player::Gun, turn::ActivePlayer};
+
+/// Debug views, each toggled with a function key.
+#[derive(Resource, Debug, Default)]
+pub struct DebugViews {
+    /// F1: collider outlines
+    pub colliders: bool,
+    /// F2: health of every brick
+    pub health: bool,
+    /// F3: aim of the active player
+    pub aim: bool,
+    /// F4: frame rate and physics body counts
+    pub stats: bool,
+}
+
+#[derive(Component)]
+struct HealthLabel;
+
+#[derive(Component)]
+struct StatsText;
+
+/// The world inspector, Rapier's debug render and the debug views. Only built with the
+/// `debug` feature.
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RapierDebugRenderPlugin::default().disabled())
+            .add_plugins(WorldInspectorPlugin::new())
+            .add_plugins(FrameTimeDiagnosticsPlugin)
+            .init_resource::<DebugViews>()
+            .add_systems(Startup, spawn_stats)
+            .add_systems(
+                Update,
+                (
+                    toggle_views,
+                    health_labels,
+                    debug_aim.run_if(|views: Res<DebugViews>| views.aim),
+                    update_stats,
+                ),
+            );
+    }
+}
+
+fn toggle_views(
+    keyboard: Res<Input<KeyCode>>,
+    mut views: ResMut<DebugViews>,
+    mut debug_render: ResMut<DebugRenderContext>,
+) {
+    let views = &mut *views;
+    for (key, view) in [
+        (KeyCode::F1, &mut views.colliders),
+        (KeyCode::F2, &mut views.health),
+        (KeyCode::F3, &mut views.aim),
+        (KeyCode::F4, &mut views.stats),
+    ] {
+        if keyboard.just_pressed(key) {
+            *view = !*view;
+        }
+    }
+    debug_render.enabled = views.colliders;
+}
+
+/// Labels every brick with its health while the view is on.
+fn health_labels(
+    mut commands: Commands,
+    views: Res<DebugViews>,
+    brick_query: Query<(Entity, Ref<BoardBrick>, Option<&Children>)>,
+    mut label_query: Query<&mut Text, With<HealthLabel>>,
+    all_labels: Query<Entity, With<HealthLabel>>,
+) {
+    if !views.health {
+        for label in all_labels.iter() {
+            commands.entity(label).despawn_recursive();
+        }
+        return;
+    }
+    for (entity, brick, children) in brick_query.iter() {
+        let text = format!("{:.0}", brick.health());
+        let label = children
+            .into_iter()
+            .flatten()
+            .find(|child| label_query.contains(**child));
+        match label {
+            Some(label) if brick.is_changed() => {
+                if let Ok(mut label) = label_query.get_mut(*label) {
+                    label.sections[0].value = text;
+                }
+            }
+            Some(_) => {}
+            None => {
+                let label = commands
+                    .spawn((
+                        Text2dBundle {
+                            text: Text::from_section(
+                                text,
+                                TextStyle {
+                                    font_size: 6.0,
+                                    color: Color::WHITE,
+                                    ..default()
+                                },
+                            ),
+                            transform: Transform::from_xyz(0.0, 0.0, 1.0),
+                            ..default()
+                        },
+                        HealthLabel,
+                    ))
+                    .id();
+                commands.entity(entity).add_child(label);
+            }
+        }
+    }
+}
+
+/// Draws the aim of the active player, and how hard they threw last time.
+fn debug_aim(player_q: Query<(&GlobalTransform, &Gun), With<ActivePlayer>>, mut gizmo: Gizmos) {
+    if let Ok((transform, gun)) = player_q.get_single() {
+        let translation = transform.translation().truncate();
+        gizmo.line_2d(translation, gun.target, Color::rgb(1.0, 0.0, 0.0));
+        if gun.last_force > 0.0 {
+            gizmo.circle_2d(
+                translation,
+                gun.last_force / 4.0,
+                Color::rgba(1.0, 1.0, 1.0, 0.3),
+            );
+        }
+    }
+}
+
+fn spawn_stats(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::GREEN,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        StatsText,
+    ));
+}
+
+fn update_stats(
+    views: Res<DebugViews>,
+    diagnostics: Res<DiagnosticsStore>,
+    rapier_config: Res<RapierConfiguration>,
+    body_query: Query<(&RigidBody, Option<&Sleeping>)>,
+    collider_query: Query<(), With<Collider>>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<StatsText>>,
+) {
+    let Ok((mut text, mut visibility)) = text_query.get_single_mut() else {
+        return;
+    };
+    if !views.stats {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Inherited;
+
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    let (mut dynamic, mut sleeping, mut fixed) = (0, 0, 0);
+    for (body, sleep) in body_query.iter() {
+        match body {
+            RigidBody::Dynamic if sleep.is_some_and(|sleep| sleep.sleeping) => sleeping += 1,
+            RigidBody::Dynamic => dynamic += 1,
+            _ => fixed += 1,
+        }
+    }
+    let time_scale = match rapier_config.timestep_mode {
+        TimestepMode::Variable { time_scale, .. }
+        | TimestepMode::Interpolated { time_scale, .. } => time_scale,
+        TimestepMode::Fixed { .. } => 1.0,
+    };
+    text.sections[0].value = format!(
+        "{:.0} fps\n{} awake, {} sleeping, {} fixed bodies\n{} colliders\ntime scale {:.2}",
+        fps,
+        dynamic,
+        sleeping,
+        fixed,
+        collider_query.iter().count(),
+        time_scale
+    );
+}
@@ -0,0 +1,239 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+use crate::{
+    board::{BoardBrick, BrickSource, FallingBrick},
+    ldtk::{self, level_px, LdtkAsset, Project},
+    player::Player,
+    Board,
+};
+
+/// Writes the current board back to an `.ldtk` project when F5 is pressed, so generated
+/// or damaged boards can be polished in the LDtk editor.
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, export_board);
+    }
+}
+
+fn export_board(
+    keyboard: Res<Input<KeyCode>>,
+    board: Res<Board>,
+    maps: Res<Assets<LdtkAsset>>,
+    brick_query: Query<(&Transform, &BoardBrick), Without<FallingBrick>>,
+    player_query: Query<(&Transform, &Player)>,
+) {
+    if !keyboard.just_pressed(KeyCode::F5) {
+        return;
+    }
+    // Terrain has no bricks to write back, it would come out as an empty level
+    if board.terrain {
+        warn!("Terrain boards cannot be exported");
+        return;
+    }
+    let Some(map) = maps.get(&board.map) else {
+        return;
+    };
+
+    let bricks = brick_query
+        .iter()
+        .map(|(transform, brick)| (transform.translation.truncate(), brick.source()));
+    let players = player_query
+        .iter()
+        .map(|(transform, player)| (transform.translation.truncate(), player.name()));
+    let project = snapshot(&map.project, board.level, bricks, players);
+
+    // Save next to the tileset so its relative path stays valid
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let level = &project.levels[board.level].identifier;
+    let path = format!("assets/{}_{}.ldtk", level, timestamp);
+    match ldtk::save(&project, &path) {
+        Ok(()) => info!(path, "Exported board"),
+        Err(err) => error!(path, %err, "Failed to export board"),
+    }
+}
+
+/// Copies `project` with the level at index `level` replaced by the given board state. Bricks
+/// go back to the tile or IntGrid layer they came from, and player entities are moved to
+/// their current positions.
+fn snapshot<'a>(
+    project: &Project,
+    level: usize,
+    bricks: impl Iterator<Item = (Vec2, BrickSource)>,
+    players: impl Iterator<Item = (Vec2, &'a str)>,
+) -> Project {
+    let mut project = project.clone();
+    let tilesets = project.defs.tilesets.clone();
+    let level = &mut project.levels[level];
+    let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+    let Some(layers) = level.layer_instances.as_mut() else {
+        return project;
+    };
+
+    // Every tile and IntGrid layer spawns bricks, destroyed ones must not come back
+    for layer in layers.iter_mut() {
+        match layer.layer_instance_type.as_str() {
+            "Tiles" => layer.grid_tiles.clear(),
+            "IntGrid" => layer.int_grid_csv.iter_mut().for_each(|value| *value = 0),
+            _ => {}
+        }
+    }
+    for (position, source) in bricks {
+        match source {
+            BrickSource::Tile { layer, tile } => {
+                let Some(layer) = layers.get_mut(layer) else {
+                    continue;
+                };
+                let tileset = layer
+                    .tileset_def_uid
+                    .and_then(|uid| tilesets.iter().find(|tileset| tileset.uid == uid));
+                if let Some(tileset) = tileset {
+                    let px = level_px(position, layer.grid_size as f32, level_size);
+                    let cell = [px[0] / layer.grid_size, px[1] / layer.grid_size];
+                    layer
+                        .grid_tiles
+                        .push(ldtk::tile_instance(tileset, tile, cell, layer.c_wid));
+                }
+            }
+            BrickSource::IntGrid { layer, value } => {
+                let Some(layer) = layers.get_mut(layer) else {
+                    continue;
+                };
+                let px = level_px(position, layer.grid_size as f32, level_size);
+                let index = px[0] / layer.grid_size + px[1] / layer.grid_size * layer.c_wid;
+                if let Some(cell) = layer.int_grid_csv.get_mut(index as usize) {
+                    *cell = value;
+                }
+            }
+        }
+    }
+
+    for (position, name) in players {
+        let entity = layers
+            .iter_mut()
+            .flat_map(|layer| {
+                let grid_size = layer.grid_size;
+                layer
+                    .entity_instances
+                    .iter_mut()
+                    .map(move |entity| (grid_size, entity))
+            })
+            .find(|(_, entity)| entity.identifier == name);
+        if let Some((grid_size, entity)) = entity {
+            let px = level_px(position, grid_size as f32, level_size);
+            entity.grid = vec![px[0] / grid_size, px[1] / grid_size];
+            entity.world_x = px[0];
+            entity.world_y = px[1];
+            entity.px = px;
+        }
+    }
+
+    project
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ldtk::convert_coords;
+
+    /// Bricks of the first level at their spawn positions, as the board plugin computes them.
+    fn bricks(project: &Project) -> Vec<(Vec2, BrickSource)> {
+        let level = &project.levels[0];
+        let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+        let layers = level.layer_instances.as_ref().unwrap();
+        layers
+            .iter()
+            .enumerate()
+            .flat_map(|(index, layer)| {
+                layer.grid_tiles.iter().map(move |tile| {
+                    let position = convert_coords(&tile.px, layer.grid_size as f32, level_size);
+                    let source = BrickSource::Tile {
+                        layer: index,
+                        tile: tile.t,
+                    };
+                    (position, source)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_restores_board() {
+        let project = ldtk::load("assets/test.ldtk").expect("failed to load ldtk file");
+        let level = &project.levels[0];
+        let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+        let layers = level.layer_instances.as_ref().unwrap();
+
+        // Spawn positions, as the player plugin computes them
+        let bricks = bricks(&project);
+        let players: Vec<_> = layers
+            .iter()
+            .flat_map(|layer| {
+                layer.entity_instances.iter().map(|entity| {
+                    let position = convert_coords(&entity.px, layer.grid_size as f32, level_size);
+                    (position, entity.identifier.as_str())
+                })
+            })
+            .collect();
+
+        let exported = snapshot(&project, 0, bricks.into_iter(), players.into_iter());
+        assert_eq!(
+            serde_json::to_value(&project.levels[0]).unwrap(),
+            serde_json::to_value(&exported.levels[0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn snapshot_keeps_bricks_in_their_layer() {
+        let mut project = ldtk::load("assets/test.ldtk").expect("failed to load ldtk file");
+        let layers = project.levels[0].layer_instances.as_mut().unwrap();
+        let index = layers
+            .iter()
+            .position(|layer| layer.layer_instance_type == "Tiles")
+            .unwrap();
+        // A second tile layer holding the lower half of the tiles
+        let mut lower = layers[index].clone();
+        let middle = layers[index].c_hei / 2 * layers[index].grid_size;
+        lower.grid_tiles.retain(|tile| tile.px[1] >= middle);
+        layers[index].grid_tiles.retain(|tile| tile.px[1] < middle);
+        layers.push(lower);
+        let count = |project: &Project, layer: usize| {
+            project.levels[0].layer_instances.as_ref().unwrap()[layer]
+                .grid_tiles
+                .len()
+        };
+        let last = layers.len() - 1;
+        let (upper_count, lower_count) = (count(&project, index), count(&project, last));
+        assert!(upper_count > 0 && lower_count > 0);
+
+        let exported = snapshot(
+            &project,
+            0,
+            bricks(&project).into_iter(),
+            std::iter::empty(),
+        );
+        assert_eq!(
+            serde_json::to_value(&project.levels[0]).unwrap(),
+            serde_json::to_value(&exported.levels[0]).unwrap()
+        );
+
+        // A destroyed brick is only missing from its own layer
+        let mut bricks = bricks(&project);
+        let destroyed = bricks
+            .iter()
+            .position(
+                |(_, source)| matches!(source, BrickSource::Tile { layer, .. } if *layer == last),
+            )
+            .unwrap();
+        bricks.remove(destroyed);
+        let exported = snapshot(&project, 0, bricks.into_iter(), std::iter::empty());
+        assert_eq!(count(&exported, index), upper_count);
+        assert_eq!(count(&exported, last), lower_count - 1);
+    }
+}
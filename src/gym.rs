@@ -0,0 +1,377 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+use bevy_rapier2d::dynamics::Sleeping;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aim,
+    board::{BoardBrick, FallingBrick},
+    ldtk::{level_px, LdtkAsset},
+    menu::AppState,
+    player::{ChargeSettings, FireEvent, Gun, Player, Shot},
+    round::{MatchScore, RoundEnding},
+    team::{Health, Team},
+    turn::{ActivePlayer, TurnClock, TurnSettings, TurnStarted},
+    weapon::{Weapon, Wind},
+    Board,
+};
+
+/// A gorilla played by another program: on each of its turns an `Observation` is written
+/// to stdout as a line of JSON, and the game waits for an `Action` line on stdin.
+#[derive(Component, Debug)]
+pub struct Bot;
+
+/// A throw, read from stdin.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Action {
+    /// Degrees counterclockwise from throwing straight to the right
+    pub angle: f32,
+    /// Capped at the maximum force of a charged throw
+    pub force: f32,
+    /// Keeps the current weapon when not given or out of shots
+    #[serde(default)]
+    pub weapon: Option<Weapon>,
+}
+
+impl Action {
+    /// The angle as the gun takes it: degrees above the horizon on the side the gorilla
+    /// faces, `facing` being `1.0` when it faces right.
+    fn gun_angle(&self, facing: f32) -> f32 {
+        let angle = if facing > 0.0 {
+            self.angle
+        } else {
+            180.0 - self.angle
+        };
+        (angle + 180.0).rem_euclid(360.0) - 180.0
+    }
+}
+
+/// The game as a bot sees it, written to stdout.
+#[derive(Debug, Clone, Serialize)]
+pub struct Observation {
+    /// The gorilla to act, or the one the round ended for
+    pub player: String,
+    pub round: u32,
+    /// Points scored by the player since their previous observation
+    pub reward: f32,
+    /// The round is over and no action is expected
+    pub done: bool,
+    /// Winning team once the round is over, `None` for a draw
+    pub winner: Option<String>,
+    /// Sideways acceleration of shots
+    pub wind: f32,
+    pub board: Grid,
+    /// Gorillas still standing
+    pub players: Vec<PlayerObservation>,
+}
+
+/// Bricks of the board. Terrain boards have no bricks and come out empty.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Grid {
+    /// Pixels per cell
+    pub cell_size: f32,
+    /// Health left of the brick in each cell as a fraction, 0 where there is none. Rows
+    /// run from the top of the level down.
+    pub cells: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerObservation {
+    pub name: String,
+    pub team: String,
+    /// Pixels from the center of the level, y pointing up
+    pub x: f32,
+    pub y: f32,
+    pub health: f32,
+    pub weapon: Weapon,
+    /// Weapons with shots left
+    pub weapons: Vec<Weapon>,
+}
+
+/// Points of each bot at its previous observation.
+#[derive(Resource, Debug, Default)]
+struct GymState {
+    points: HashMap<String, f32>,
+    /// Bot whose turn has started, observed once the previous shot has played out
+    waiting: Option<Entity>,
+}
+
+/// Action read for the active bot, applied after reading.
+#[derive(Resource, Debug)]
+struct PendingAction(Entity, Action);
+
+/// Lets programs play the gorillas marked `Bot`, one turn per step.
+pub struct GymPlugin;
+
+impl Plugin for GymPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GymState>()
+            .add_systems(
+                Update,
+                (
+                    // The round is decided, bots hear about it when it is over
+                    observe.run_if(not(resource_exists::<RoundEnding>())),
+                    act.run_if(resource_exists::<PendingAction>()),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Playing)),
+            )
+            .add_systems(OnExit(AppState::Playing), finish_round);
+    }
+}
+
+#[derive(SystemParam)]
+struct Observer<'w, 's> {
+    board: Res<'w, Board>,
+    maps: Res<'w, Assets<LdtkAsset>>,
+    wind: Res<'w, Wind>,
+    score: Res<'w, MatchScore>,
+    state: ResMut<'w, GymState>,
+    brick_query: Query<'w, 's, (&'static Transform, &'static BoardBrick)>,
+    player_query: Query<
+        'w,
+        's,
+        (
+            &'static GlobalTransform,
+            &'static Player,
+            &'static Team,
+            &'static Health,
+            &'static Gun,
+        ),
+    >,
+}
+
+impl Observer<'_, '_> {
+    fn grid(&self) -> Grid {
+        let Some(level) = self
+            .maps
+            .get(&self.board.map)
+            .and_then(|map| map.project.levels.get(self.board.level))
+        else {
+            return Grid::default();
+        };
+        let Some(layer) = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .find(|layer| layer.layer_instance_type != "Entities")
+        else {
+            return Grid::default();
+        };
+        let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+        let mut cells = vec![vec![0.0; layer.c_wid as usize]; layer.c_hei as usize];
+        for (transform, brick) in self.brick_query.iter() {
+            let px = level_px(
+                transform.translation.truncate(),
+                layer.grid_size as f32,
+                level_size,
+            );
+            let (x, y) = (px[0] / layer.grid_size, px[1] / layer.grid_size);
+            if let Some(cell) = usize::try_from(y)
+                .ok()
+                .and_then(|y| cells.get_mut(y))
+                .zip(usize::try_from(x).ok())
+                .and_then(|(row, x)| row.get_mut(x))
+            {
+                *cell = f32::max(*cell, brick.health() / brick.max_health());
+            }
+        }
+        Grid {
+            cell_size: layer.grid_size as f32,
+            cells,
+        }
+    }
+
+    fn observe(&mut self, player: &str, done: bool, winner: Option<String>) -> Observation {
+        let points = self
+            .score
+            .players
+            .get(player)
+            .map(|score| score.points)
+            .unwrap_or_default();
+        let previous = self.state.points.insert(player.to_string(), points);
+        let players = self
+            .player_query
+            .iter()
+            .map(|(transform, player, team, health, gun)| {
+                let position = transform.translation();
+                PlayerObservation {
+                    name: player.name().to_string(),
+                    team: team.0.clone(),
+                    x: position.x,
+                    y: position.y,
+                    health: health.0,
+                    weapon: gun.weapon,
+                    weapons: Weapon::ALL
+                        .into_iter()
+                        .filter(|weapon| gun.has_ammo(*weapon))
+                        .collect(),
+                }
+            })
+            .collect();
+        Observation {
+            player: player.to_string(),
+            round: self.score.round,
+            reward: points - previous.unwrap_or_default(),
+            done,
+            winner,
+            wind: self.wind.0,
+            board: self.grid(),
+            players,
+        }
+    }
+}
+
+fn send(observation: &Observation) {
+    let mut stdout = std::io::stdout().lock();
+    let written = serde_json::to_writer(&mut stdout, observation)
+        .map_err(std::io::Error::from)
+        .and_then(|()| writeln!(stdout))
+        .and_then(|()| stdout.flush());
+    if let Err(err) = written {
+        error!(%err, "Failed to write observation");
+    }
+}
+
+/// Reads actions from stdin until a valid one comes, `None` once stdin is closed.
+fn receive() -> Option<Action> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match std::io::stdin().lock().read_line(&mut line) {
+            Ok(0) => return None,
+            Ok(_) => match serde_json::from_str(&line) {
+                Ok(action) => return Some(action),
+                Err(err) => error!(%err, line = line.trim(), "Ignoring malformed action"),
+            },
+            Err(err) => {
+                error!(%err, "Failed to read action");
+                return None;
+            }
+        }
+    }
+}
+
+/// Sends the observation once a bot's turn has started and the board has settled, and
+/// blocks until its action is read, so the game stands still while the bot thinks.
+///
+/// The turn passes as soon as a shot is thrown, so without waiting the bot would see the
+/// board before the previous shot has landed.
+#[allow(clippy::too_many_arguments)]
+fn observe(
+    mut commands: Commands,
+    mut turn_started: EventReader<TurnStarted>,
+    mut observer: Observer,
+    settings: Res<TurnSettings>,
+    mut clock: ResMut<TurnClock>,
+    bot_query: Query<&Player, (With<Bot>, With<ActivePlayer>)>,
+    shot_query: Query<(), With<Shot>>,
+    falling_query: Query<Option<&Sleeping>, With<FallingBrick>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(event) = turn_started.read().last() {
+        observer.state.waiting = Some(event.player);
+    }
+    let Some(bot) = observer.state.waiting else {
+        return;
+    };
+    // Debris fragments are falling bricks as well
+    let settled = shot_query.is_empty()
+        && falling_query
+            .iter()
+            .all(|sleeping| sleeping.is_some_and(|sleeping| sleeping.sleeping));
+    if !settled {
+        // The bot's time only starts once it has seen the board
+        clock.restart(&settings);
+        return;
+    }
+    observer.state.waiting = None;
+    // Not a bot, or knocked out while the board settled
+    let Ok(player) = bot_query.get(bot) else {
+        return;
+    };
+
+    send(&observer.observe(player.name(), false, None));
+    match receive() {
+        Some(action) => commands.insert_resource(PendingAction(bot, action)),
+        None => {
+            info!("Stdin closed, exiting");
+            exit.send(AppExit);
+        }
+    }
+}
+
+fn act(
+    mut commands: Commands,
+    pending: Res<PendingAction>,
+    charge: Res<ChargeSettings>,
+    mut fire: EventWriter<FireEvent>,
+    mut player_query: Query<(&GlobalTransform, &mut Gun), With<ActivePlayer>>,
+) {
+    commands.remove_resource::<PendingAction>();
+    let PendingAction(player, action) = &*pending;
+    let Ok((transform, mut gun)) = player_query.get_mut(*player) else {
+        return;
+    };
+    if let Some(weapon) = action.weapon.filter(|weapon| gun.has_ammo(*weapon)) {
+        gun.weapon = weapon;
+    }
+    let position = transform.translation().truncate();
+    gun.angle = action.gun_angle(aim::facing(position));
+    gun.target = aim::aim_target(position, gun.angle);
+    gun.force = action.force.clamp(0.0, charge.max_force);
+    fire.send(FireEvent);
+}
+
+/// Sends each bot the last observation of the round, before the board is cleared.
+fn finish_round(mut observer: Observer, bot_query: Query<&Player, With<Bot>>) {
+    observer.state.waiting = None;
+    let winner = observer
+        .score
+        .winners
+        .last()
+        .cloned()
+        .flatten()
+        .map(|team| team.0);
+    // Knocked out bots are gone, and bots still standing may not have had a turn yet
+    let mut bots: Vec<String> = observer.state.points.keys().cloned().collect();
+    for player in bot_query.iter() {
+        if !bots.iter().any(|bot| bot == player.name()) {
+            bots.push(player.name().to_string());
+        }
+    }
+    bots.sort();
+    for bot in bots {
+        send(&observer.observe(&bot, true, winner.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_are_parsed() {
+        let action: Action = serde_json::from_str(r#"{"angle": 90, "force": 50}"#).unwrap();
+        assert_eq!(action.weapon, None);
+        assert_eq!(action.gun_angle(1.0), 90.0);
+        assert_eq!(action.gun_angle(-1.0), 90.0);
+
+        let action: Action =
+            serde_json::from_str(r#"{"angle": 180, "force": 50, "weapon": "bunch"}"#).unwrap();
+        assert_eq!(action.weapon, Some(Weapon::Bunch));
+        assert_eq!(action.gun_angle(1.0), -180.0);
+        assert_eq!(action.gun_angle(-1.0), 0.0);
+
+        // Up and to the left is above the horizon for a gorilla facing left
+        let action: Action = serde_json::from_str(r#"{"angle": 135, "force": 50}"#).unwrap();
+        assert_eq!(action.gun_angle(-1.0), 45.0);
+        assert_eq!(action.gun_angle(1.0), 135.0);
+        let action: Action = serde_json::from_str(r#"{"angle": 300, "force": 50}"#).unwrap();
+        assert_eq!(action.gun_angle(1.0), -60.0);
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{player::Player, turn::TurnOrder};
+
+/// Things a player can do, independent of the device they play with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Held to build up force, the shot is fired when it is released
+    Charge,
+    /// Turns the aim slower while held
+    FineAim,
+    SwitchWeapon,
+    SwitchAimMode,
+    Jump,
+}
+
+/// Analog inputs, in the range `-1.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActionAxis {
+    /// Turns the aim upwards for positive values
+    Aim,
+    /// Walks to the right for positive values
+    Move,
+}
+
+const KEY_BINDINGS: [(KeyCode, Action); 6] = [
+    (KeyCode::Space, Action::Charge),
+    (KeyCode::ShiftLeft, Action::FineAim),
+    (KeyCode::ShiftRight, Action::FineAim),
+    (KeyCode::Q, Action::SwitchWeapon),
+    (KeyCode::Tab, Action::SwitchAimMode),
+    (KeyCode::W, Action::Jump),
+];
+
+const MOUSE_BINDINGS: [(MouseButton, Action); 1] = [(MouseButton::Left, Action::Charge)];
+
+const BUTTON_BINDINGS: [(GamepadButtonType, Action); 6] = [
+    (GamepadButtonType::South, Action::Charge),
+    (GamepadButtonType::RightTrigger2, Action::Charge),
+    (GamepadButtonType::LeftTrigger2, Action::FineAim),
+    (GamepadButtonType::North, Action::SwitchWeapon),
+    (GamepadButtonType::Select, Action::SwitchAimMode),
+    (GamepadButtonType::East, Action::Jump),
+];
+
+/// Keys for the negative and positive direction of an axis.
+const KEY_AXES: [(KeyCode, KeyCode, ActionAxis); 3] = [
+    (KeyCode::Down, KeyCode::Up, ActionAxis::Aim),
+    (KeyCode::Left, KeyCode::Right, ActionAxis::Move),
+    (KeyCode::A, KeyCode::D, ActionAxis::Move),
+];
+
+const BUTTON_AXES: [(GamepadButtonType, GamepadButtonType, ActionAxis); 2] = [
+    (
+        GamepadButtonType::DPadDown,
+        GamepadButtonType::DPadUp,
+        ActionAxis::Aim,
+    ),
+    (
+        GamepadButtonType::DPadLeft,
+        GamepadButtonType::DPadRight,
+        ActionAxis::Move,
+    ),
+];
+
+const STICK_AXES: [(GamepadAxisType, ActionAxis); 2] = [
+    (GamepadAxisType::RightStickY, ActionAxis::Aim),
+    (GamepadAxisType::LeftStickX, ActionAxis::Move),
+];
+
+/// The device a player is controlled with.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad(Gamepad),
+    /// Played by the computer, no actions are read
+    Computer,
+}
+
+/// The actions of a player this frame, read from their [`InputDevice`].
+#[derive(Component, Debug, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    previous: HashSet<Action>,
+    axes: HashMap<ActionAxis, f32>,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action) && !self.previous.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        !self.pressed.contains(&action) && self.previous.contains(&action)
+    }
+
+    pub fn axis(&self, axis: ActionAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    fn update(&mut self, pressed: HashSet<Action>, axes: HashMap<ActionAxis, f32>) {
+        self.previous = std::mem::replace(&mut self.pressed, pressed);
+        self.axes = axes
+            .into_iter()
+            .map(|(axis, value)| (axis, value.clamp(-1.0, 1.0)))
+            .collect();
+    }
+}
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            (bind_gamepads, read_actions).chain().after(InputSystem),
+        );
+    }
+}
+
+/// Gives each connected gamepad to a player, in turn order. The first player keeps the
+/// keyboard and mouse, and players whose gamepad is disconnected fall back to them.
+fn bind_gamepads(
+    gamepads: Res<Gamepads>,
+    order: Res<TurnOrder>,
+    mut q_player: Query<(&mut InputDevice, &Player)>,
+) {
+    let mut bound: Vec<Gamepad> = Vec::new();
+    for (mut device, player) in q_player.iter_mut() {
+        if let InputDevice::Gamepad(gamepad) = *device {
+            if gamepads.contains(gamepad) {
+                bound.push(gamepad);
+            } else {
+                info!(player = player.name(), "Lost their gamepad");
+                *device = InputDevice::KeyboardMouse;
+            }
+        }
+    }
+
+    let mut free = gamepads.iter().filter(|gamepad| !bound.contains(gamepad));
+    for entity in order.players().skip(1) {
+        let Ok((mut device, player)) = q_player.get_mut(entity) else {
+            continue;
+        };
+        if *device != InputDevice::KeyboardMouse {
+            continue;
+        }
+        let Some(gamepad) = free.next() else {
+            break;
+        };
+        info!(
+            player = player.name(),
+            gamepad = gamepad.id,
+            "Bound gamepad"
+        );
+        *device = InputDevice::Gamepad(gamepad);
+    }
+}
+
+fn read_actions(
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    buttons: Res<Input<GamepadButton>>,
+    sticks: Res<Axis<GamepadAxis>>,
+    mut query: Query<(&InputDevice, &mut ActionState)>,
+) {
+    for (device, mut state) in query.iter_mut() {
+        let mut pressed = HashSet::new();
+        let mut axes: HashMap<ActionAxis, f32> = HashMap::new();
+        match *device {
+            InputDevice::KeyboardMouse => {
+                for (key, action) in KEY_BINDINGS {
+                    if keyboard.pressed(key) {
+                        pressed.insert(action);
+                    }
+                }
+                for (button, action) in MOUSE_BINDINGS {
+                    if mouse.pressed(button) {
+                        pressed.insert(action);
+                    }
+                }
+                for (negative, positive, axis) in KEY_AXES {
+                    let value =
+                        keyboard.pressed(positive) as i32 - keyboard.pressed(negative) as i32;
+                    *axes.entry(axis).or_default() += value as f32;
+                }
+            }
+            InputDevice::Gamepad(gamepad) => {
+                let button = |button_type| GamepadButton::new(gamepad, button_type);
+                for (button_type, action) in BUTTON_BINDINGS {
+                    if buttons.pressed(button(button_type)) {
+                        pressed.insert(action);
+                    }
+                }
+                for (negative, positive, axis) in BUTTON_AXES {
+                    let value = buttons.pressed(button(positive)) as i32
+                        - buttons.pressed(button(negative)) as i32;
+                    *axes.entry(axis).or_default() += value as f32;
+                }
+                for (axis_type, axis) in STICK_AXES {
+                    let value = sticks
+                        .get(GamepadAxis::new(gamepad, axis_type))
+                        .unwrap_or(0.0);
+                    *axes.entry(axis).or_default() += value;
+                }
+            }
+            InputDevice::Computer => {}
+        }
+        state.update(pressed, axes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releasing_charge_fires_once() {
+        let mut state = ActionState::default();
+        state.update(HashSet::from([Action::Charge]), HashMap::new());
+        assert!(state.just_pressed(Action::Charge));
+        state.update(HashSet::from([Action::Charge]), HashMap::new());
+        assert!(state.pressed(Action::Charge) && !state.just_pressed(Action::Charge));
+        state.update(HashSet::new(), HashMap::new());
+        assert!(state.just_released(Action::Charge));
+        state.update(HashSet::new(), HashMap::new());
+        assert!(!state.just_released(Action::Charge));
+    }
+
+    #[test]
+    fn axes_are_clamped() {
+        let mut state = ActionState::default();
+        state.update(HashSet::new(), HashMap::from([(ActionAxis::Move, 2.0)]));
+        assert_eq!(state.axis(ActionAxis::Move), 1.0);
+        assert_eq!(state.axis(ActionAxis::Aim), 0.0);
+    }
+}
@@ -25,7 +25,7 @@ pub struct LdtkJson {
     /// This object is not actually used by LDtk. It ONLY exists to force explicit references to
     /// all types, to make sure QuickType finds them and integrate all of them. Otherwise,
     /// Quicktype will drop types that are not explicitely used.
-    #[serde(rename = "__FORCED_REFS")]
+    #[serde(rename = "__FORCED_REFS", skip_serializing_if = "Option::is_none")]
     pub forced_refs: Option<ForcedRefs>,
 
     /// LDtk application build identifier.<br/>  This is only used to identify the LDtk version
@@ -5,8 +5,12 @@ use bevy::{
     reflect::TypePath,
     utils::BoxedFuture,
 };
-use serde::Deserialize;
-use std::{io::BufReader, path::Path};
+use serde::{Deserialize, Serialize};
+use serde_json::ser::PrettyFormatter;
+use std::{
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
 use thiserror::Error;
 
 mod ldtk_schema;
@@ -19,6 +23,64 @@ pub fn load(path: impl AsRef<Path>) -> Result<Project, std::io::Error> {
     Ok(serde_json::from_reader::<_, Project>(reader)?)
 }
 
+/// Writes a project in the same layout as the LDtk editor, so it can be opened there.
+pub fn save(project: &Project, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(std::fs::File::create(path)?);
+    let mut serializer =
+        serde_json::Serializer::with_formatter(&mut writer, PrettyFormatter::with_indent(b"\t"));
+    project.serialize(&mut serializer)?;
+    writer.flush()
+}
+
+/// Builds the tile instance placing tile `t` of `tileset` at `cell` in a layer `columns` wide.
+pub fn tile_instance(
+    tileset: &TilesetDefinition,
+    t: i64,
+    cell: [i64; 2],
+    columns: i64,
+) -> TileInstance {
+    let size = tileset.tile_grid_size;
+    TileInstance {
+        a: 1.0,
+        d: vec![cell[0] + cell[1] * columns],
+        f: 0,
+        px: vec![cell[0] * size, cell[1] * size],
+        src: vec![(t % tileset.c_wid) * size, (t / tileset.c_wid) * size],
+        t,
+    }
+}
+
+/// Returns the enum values a tileset tags the given tile with.
+pub fn tile_enum_tags(project: &Project, tileset_uid: i64, tile_id: i64) -> Vec<&str> {
+    project
+        .defs
+        .tilesets
+        .iter()
+        .filter(|tileset| tileset.uid == tileset_uid)
+        .flat_map(|tileset| &tileset.enum_tags)
+        .filter(|tag| tag.tile_ids.contains(&tile_id))
+        .map(|tag| tag.enum_value_id.as_str())
+        .collect()
+}
+
+/// Returns the identifier of an IntGrid value of the given layer definition, if it has one.
+pub fn int_grid_value_identifier(
+    project: &Project,
+    layer_def_uid: i64,
+    value: i64,
+) -> Option<&str> {
+    project
+        .defs
+        .layers
+        .iter()
+        .find(|layer| layer.uid == layer_def_uid)?
+        .int_grid_values
+        .iter()
+        .find(|int_grid_value| int_grid_value.value == value)?
+        .identifier
+        .as_deref()
+}
+
 #[derive(Asset, TypePath, Debug, Deserialize)]
 pub struct LdtkAsset {
     pub project: Project,
@@ -47,15 +109,18 @@ impl AssetLoader for LdtkAssetLoader {
         &'a self,
         reader: &'a mut Reader,
         _settings: &'a (),
-        _load_context: &'a mut LoadContext,
+        load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
-            let custom_asset = serde_json::from_slice(&bytes)?;
-            Ok(LdtkAsset {
-                project: custom_asset,
-            })
+            let project: Project = serde_json::from_slice(&bytes)?;
+            debug!(
+                path = %load_context.path().display(),
+                levels = project.levels.len(),
+                "Loaded LDtk project"
+            );
+            Ok(LdtkAsset { project })
         })
     }
 
@@ -68,6 +133,20 @@ impl AssetLoader for LdtkAssetLoader {
 mod tests {
     use super::*;
 
+    #[test]
+    fn save_roundtrip() {
+        let project = load("assets/test.ldtk").expect("failed to load ldtk file");
+        let path = std::env::temp_dir().join("gorillas_save_roundtrip.ldtk");
+        save(&project, &path).expect("failed to save ldtk file");
+        let saved = load(&path).expect("failed to load saved ldtk file");
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(
+            serde_json::to_value(&project).unwrap(),
+            serde_json::to_value(&saved).unwrap()
+        );
+    }
+
     #[test]
     fn parse_ldtk() {
         let file = "assets/test.ldtk";
@@ -101,3 +180,13 @@ pub fn convert_coords(px: &[i64], grid_size: f32, level_size: Vec2) -> Vec2 {
 
     Vec2::new(center_x, center_y)
 }
+
+/// Inverse of [`convert_coords`]: the grid aligned pixel coordinates of the cell containing
+/// `position`.
+pub fn level_px(position: Vec2, grid_size: f32, level_size: Vec2) -> Vec<i64> {
+    let px_x = position.x + level_size.x / 2.0 - grid_size / 2.0;
+    let px_y = level_size.y / 2.0 - position.y - grid_size / 2.0;
+
+    let snap = |v: f32| ((v / grid_size).round() * grid_size) as i64;
+    vec![snap(px_x), snap(px_y)]
+}
@@ -0,0 +1,32 @@
+use std::{error::Error, fs::File, path::Path, sync::Mutex};
+
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+/// Filter used when neither `--log` nor `RUST_LOG` is set, the same as Bevy's
+const DEFAULT_FILTER: &str = "info,wgpu=error,naga=warn";
+
+/// Sends logs to stderr, and as JSON lines to `json_file` when given, in place of Bevy's
+/// `LogPlugin`. Events are filtered by `filter`, else `RUST_LOG`, else `DEFAULT_FILTER`.
+/// Targets are module paths, so `gorillas::board=debug,info` shows the board in detail.
+pub fn init(filter: Option<&str>, json_file: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let filter = match filter {
+        Some(filter) => EnvFilter::try_new(filter)?,
+        None => {
+            EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(DEFAULT_FILTER))?
+        }
+    };
+    let json = match json_file {
+        Some(path) => Some(
+            fmt::layer()
+                .json()
+                .with_writer(Mutex::new(File::create(path)?)),
+        ),
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(json)
+        .try_init()?;
+    Ok(())
+}
@@ -1,76 +1,273 @@
+mod ai;
+mod aim;
+mod audio;
 mod board;
+mod camera;
+mod cli;
+mod config;
+#[cfg(feature = "debug")]
+mod debug;
+mod export;
+mod gym;
+mod input;
 mod ldtk;
+mod logging;
+mod menu;
+mod movement;
 mod player;
+mod replay;
+mod round;
+mod skyline;
+mod stats;
+mod team;
+mod turn;
+mod weapon;
 
-use bevy::{prelude::*, render::camera::ScalingMode};
-use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use ai::AiPlugin;
+use aim::AimPlugin;
+use audio::GameAudioPlugin;
+use std::time::Duration;
+
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    log::LogPlugin,
+    prelude::*,
+    render::{
+        camera::ScalingMode,
+        settings::{RenderCreation, WgpuSettings},
+        RenderPlugin,
+    },
+    time::TimeUpdateStrategy,
+    window::{ExitCondition, WindowResolution},
+    winit::WinitPlugin,
+};
 use bevy_rapier2d::prelude::*;
 use board::BoardPlugin;
+use camera::CameraPlugin;
+use clap::Parser;
+use cli::{Cli, CliPlugin, StartFailed};
+use config::Config;
+use export::ExportPlugin;
+use gym::GymPlugin;
+use input::ActionPlugin;
 use ldtk::{LdtkAsset, LdtkAssetLoader};
-use player::PlayerPlugin;
+use menu::{AppState, MatchSettings, MenuPlugin};
+use movement::MovementPlugin;
+use player::{Player, PlayerPlugin};
+use replay::{Replay, ReplayPlugin};
+use round::{MatchScore, RoundPlugin};
+use skyline::SkylineSettings;
+use stats::StatsPlugin;
+use team::{PlayerEliminated, Team, TeamPlugin};
+use turn::TurnPlugin;
+use weapon::WeaponPlugin;
+
+/// Frames per simulated second of the headless game loop, which runs as fast as it can
+const HEADLESS_FPS: f64 = 60.0;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(10.0))
-        .add_plugins(RapierDebugRenderPlugin::default())
-        .add_plugins(WorldInspectorPlugin::new())
+    let mut cli = Cli::parse();
+    if let Err(err) = logging::init(cli.log.as_deref(), cli.log_file.as_deref()) {
+        eprintln!("Failed to set up logging: {}", err);
+    }
+    let mut config = match &cli.config {
+        Some(path) => Config::load(path).unwrap_or_else(|err| {
+            warn!(path = %path.display(), %err, "Ignoring config");
+            Config::default()
+        }),
+        None => Config::from_env(),
+    };
+    config.apply_env();
+    let replay = cli.replay.as_ref().map(|path| {
+        Replay::load(path).unwrap_or_else(|err| {
+            error!(path = %path.display(), %err, "Failed to read replay");
+            std::process::exit(1);
+        })
+    });
+    // Replays are played on their own level unless told otherwise
+    if let Some(replay) = replay
+        .as_ref()
+        .filter(|_| cli.level.is_none() && cli.seed.is_none())
+    {
+        cli.level = replay.level.clone();
+        cli.level_id = replay.level_id.clone().filter(|_| cli.level.is_some());
+        cli.seed = replay.seed;
+        cli.terrain |= replay.terrain;
+    }
+
+    let start_failed = StartFailed::default();
+    let mut app = App::new();
+    app.insert_resource(config)
+        .insert_resource(start_failed.clone());
+    if cli.headless || cli.gym {
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    ..default()
+                })
+                .set(RenderPlugin {
+                    render_creation: RenderCreation::Automatic(WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }),
+                })
+                .disable::<WinitPlugin>()
+                .disable::<LogPlugin>(),
+        )
+        .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::ZERO))
+        // Every frame advances time by the same step, however long it took to compute, so
+        // matches are only limited by the CPU
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / HEADLESS_FPS,
+        )))
+        .add_systems(Startup, fix_physics_step);
+    } else {
+        let mut window = Window::default();
+        if let Some((width, height)) = cli.window_size {
+            window.resolution = WindowResolution::new(width, height);
+        }
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(window),
+                    ..default()
+                })
+                .disable::<LogPlugin>(),
+        )
+        .add_plugins(GameAudioPlugin);
+        #[cfg(feature = "debug")]
+        app.add_plugins(debug::DebugPlugin);
+    }
+
+    app.add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(10.0))
         .add_plugins(BoardPlugin)
         .add_plugins(PlayerPlugin)
+        .add_plugins(AimPlugin)
+        .add_plugins(ActionPlugin)
+        .add_plugins(ExportPlugin)
+        .add_plugins(TurnPlugin)
+        .add_plugins(TeamPlugin)
+        .add_plugins(MovementPlugin)
+        .add_plugins(WeaponPlugin)
+        .add_plugins(CameraPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(AiPlugin)
+        .add_plugins(RoundPlugin)
+        .add_plugins(CliPlugin(cli.clone()))
+        .add_plugins(ReplayPlugin {
+            replay,
+            record: cli.record.clone(),
+        })
+        .add_plugins(StatsPlugin {
+            path: cli.stats.clone(),
+        })
         .init_asset::<LdtkAsset>()
         .init_asset_loader::<LdtkAssetLoader>()
+        .init_resource::<Board>()
+        .init_resource::<Players>()
         .add_systems(Startup, setup)
-        .add_systems(Update, despawn_far_away)
-        .run();
+        .add_systems(OnEnter(AppState::Playing), start_round)
+        .add_systems(Update, despawn_far_away);
+    if cli.gym {
+        app.add_plugins(GymPlugin);
+    }
+    app.run();
+    if start_failed.get() {
+        std::process::exit(1);
+    }
 }
 
 #[derive(Resource, Default)]
 pub struct Board {
     map: Handle<LdtkAsset>,
+    /// Index of the level in the project
+    level: usize,
     loaded: bool,
+    /// Play on pixel terrain with craters instead of bricks
+    terrain: bool,
 }
 
 #[derive(Resource, Default)]
 pub struct Players {
     map: Handle<LdtkAsset>,
+    level: usize,
     loaded: bool,
-    names: Vec<String>,
-    current_player: Option<usize>,
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// Steps the physics by the same time every frame in headless games.
+fn fix_physics_step(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.timestep_mode = TimestepMode::Fixed {
+        dt: (1.0 / HEADLESS_FPS) as f32,
+        substeps: 1,
+    };
+}
+
+fn setup(mut commands: Commands) {
     let mut camera_bundle = Camera2dBundle::default();
     camera_bundle.projection.scaling_mode = ScalingMode::FixedVertical(256.0);
     commands.spawn(camera_bundle);
+}
 
-    // Load the map
-    let map_handle = asset_server.load("test.ldtk");
+/// Spawns the board and players of the level picked in the menu.
+fn start_round(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    score: Res<MatchScore>,
+    mut maps: ResMut<Assets<LdtkAsset>>,
+) {
+    info!(
+        round = score.round,
+        level = settings.level.name,
+        "Starting round"
+    );
+    let mut map = settings.level.map.clone();
+    // Random skylines are different every round
+    if let Some(seed) = settings.level.seed.filter(|_| score.round > 1) {
+        let seed = seed.wrapping_add(score.round as u64 - 1);
+        debug!(seed, "Generating skyline");
+        map = maps.add(LdtkAsset {
+            project: skyline::generate(&SkylineSettings::with_seed(seed)),
+        });
+    }
     commands.insert_resource(Board {
-        map: map_handle.clone(),
+        map: map.clone(),
+        level: settings.level.level,
         loaded: false,
+        terrain: settings.terrain,
     });
     commands.insert_resource(Players {
-        map: map_handle.clone(),
+        map,
+        level: settings.level.level,
         loaded: false,
-        names: Vec::new(),
-        current_player: None,
     });
 }
 
 fn despawn_far_away(
     mut commands: Commands,
-    shot_quuery: Query<(Entity, &Transform), Without<Camera>>,
+    shot_quuery: Query<(Entity, &Transform, Option<(&Player, &Team)>), Without<Camera>>,
     camera_query: Query<&Transform, With<Camera>>,
+    mut eliminated_writer: EventWriter<PlayerEliminated>,
 ) {
-    for (e, transform) in shot_quuery.iter() {
+    for (e, transform, player) in shot_quuery.iter() {
         // Shot is 100 units away from all cameras.
         let do_despawn = camera_query.iter().all(|c| {
             let distance = c.translation - transform.translation;
             distance.length() > 1000.0
         });
         if do_despawn {
-            eprintln!("Despawning entity {:?} due to distance", e);
+            debug!(entity = ?e, "Despawning far away entity");
             commands.entity(e).despawn_recursive();
+            // A gorilla that walked or fell out of the level is out of the round
+            if let Some((player, team)) = player {
+                info!(player = player.name(), team = team.0, "Left the level");
+                eliminated_writer.send(PlayerEliminated {
+                    player: e,
+                    team: team.clone(),
+                    shooter: None,
+                });
+            }
         }
     }
 }
@@ -0,0 +1,410 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera::CameraSettings,
+    ldtk::LdtkAsset,
+    player::level_players,
+    skyline::{self, SkylineSettings},
+};
+
+/// Folder searched for `.ldtk` files to play on
+const ASSET_DIR: &str = "assets";
+const MAX_ROUNDS: u32 = 9;
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AppState {
+    /// Picking the level and players
+    #[default]
+    Menu,
+    Playing,
+    /// Showing the scores between rounds
+    RoundOver,
+}
+
+/// Who plays a gorilla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Controller {
+    #[default]
+    Human,
+    Computer,
+    /// Another program, over stdin and stdout. Only set from the command line.
+    External,
+}
+
+/// Weapons the players get.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum WeaponSet {
+    /// Only bananas, like the original game
+    Classic,
+    #[default]
+    Full,
+}
+
+/// A level of one of the LDtk projects.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelChoice {
+    pub name: String,
+    pub map: Handle<LdtkAsset>,
+    /// Index into the levels of the project
+    pub level: usize,
+    /// Seed of a generated skyline, a new one is generated for every round
+    pub seed: Option<u64>,
+}
+
+/// Everything picked in the menu. The board is spawned from `level` and the players read
+/// their controllers and weapons from it.
+#[derive(Resource, Debug, Clone)]
+pub struct MatchSettings {
+    pub level: LevelChoice,
+    pub rounds: u32,
+    /// Controller of each gorilla of the level, in the order they appear in it
+    pub slots: Vec<Controller>,
+    pub wind: bool,
+    pub weapons: WeaponSet,
+    /// Play on pixel terrain with craters instead of bricks
+    pub terrain: bool,
+    /// Nobody is watching: rounds follow each other without waiting for Enter, and the
+    /// game exits when the match is over
+    pub unattended: bool,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            level: LevelChoice::default(),
+            rounds: 1,
+            slots: Vec::new(),
+            wind: false,
+            weapons: WeaponSet::default(),
+            terrain: false,
+            unattended: false,
+        }
+    }
+}
+
+impl MatchSettings {
+    pub fn controller(&self, slot: usize) -> Controller {
+        self.slots.get(slot).copied().unwrap_or_default()
+    }
+}
+
+/// Projects that can be played, loaded at startup.
+#[derive(Resource, Debug, Default)]
+struct LevelFiles(Vec<(String, Handle<LdtkAsset>)>);
+
+/// Levels of the projects loaded so far.
+#[derive(Resource, Debug, Default)]
+struct LevelList(Vec<LevelChoice>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuRow {
+    Level,
+    Rounds,
+    Slot(usize),
+    Wind,
+    Weapons,
+    Terrain,
+    /// Camera shake and hit-stop, which is not a match setting
+    ReducedMotion,
+    Start,
+}
+
+/// Row of the menu under the cursor.
+#[derive(Resource, Debug, Default)]
+struct MenuCursor(usize);
+
+#[derive(Component)]
+struct MenuRoot;
+
+#[derive(Component)]
+struct MenuText;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .init_resource::<MatchSettings>()
+            .init_resource::<LevelFiles>()
+            .init_resource::<LevelList>()
+            .init_resource::<MenuCursor>()
+            .add_systems(Startup, load_level_files)
+            .add_systems(OnEnter(AppState::Menu), spawn_menu)
+            .add_systems(OnExit(AppState::Menu), despawn_menu)
+            .add_systems(
+                Update,
+                (list_levels, navigate_menu, update_menu)
+                    .chain()
+                    .run_if(in_state(AppState::Menu)),
+            );
+    }
+}
+
+/// Loads every `.ldtk` file in the assets folder, and generates a random skyline, seeded by
+/// `GORILLAS_SKYLINE_SEED` when it is set.
+fn load_level_files(
+    asset_server: Res<AssetServer>,
+    mut maps: ResMut<Assets<LdtkAsset>>,
+    mut files: ResMut<LevelFiles>,
+    mut list: ResMut<LevelList>,
+    mut settings: ResMut<MatchSettings>,
+) {
+    let mut paths: Vec<String> = std::fs::read_dir(ASSET_DIR)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|name| name.ends_with(".ldtk"))
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    for path in paths {
+        let handle = asset_server.load(path.clone());
+        files.0.push((path, handle));
+    }
+
+    let seed = std::env::var("GORILLAS_SKYLINE_SEED").ok();
+    let seed = seed
+        .as_ref()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(rand::random);
+    let skyline = maps.add(LdtkAsset {
+        project: skyline::generate(&SkylineSettings::with_seed(seed)),
+    });
+    let skyline = LevelChoice {
+        name: format!("Random skyline ({})", seed),
+        map: skyline,
+        level: 0,
+        seed: Some(seed),
+    };
+    list.0.push(skyline.clone());
+
+    settings.level = match std::env::var_os("GORILLAS_SKYLINE_SEED") {
+        Some(_) => skyline,
+        None => LevelChoice {
+            name: "test.ldtk".to_string(),
+            map: asset_server.load("test.ldtk"),
+            level: 0,
+            seed: None,
+        },
+    };
+}
+
+/// Adds the levels of the projects that finished loading.
+fn list_levels(
+    files: Res<LevelFiles>,
+    maps: Res<Assets<LdtkAsset>>,
+    mut list: ResMut<LevelList>,
+    mut settings: ResMut<MatchSettings>,
+) {
+    for (path, handle) in &files.0 {
+        if list.0.iter().any(|choice| choice.map == *handle) {
+            continue;
+        }
+        let Some(map) = maps.get(handle) else {
+            continue;
+        };
+        for (index, level) in map.project.levels.iter().enumerate() {
+            let name = match map.project.levels.len() {
+                1 => path.clone(),
+                _ => format!("{} / {}", path, level.identifier),
+            };
+            let choice = LevelChoice {
+                name,
+                map: handle.clone(),
+                level: index,
+                seed: None,
+            };
+            // Take the display name of the default level
+            if settings.level.map == choice.map && settings.level.level == index {
+                settings.level = choice.clone();
+            }
+            list.0.push(choice);
+        }
+    }
+}
+
+fn spawn_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.0, 0.0, 0.4).into(),
+                // Covers the game HUD
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            MenuRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), MenuText));
+        });
+}
+
+fn despawn_menu(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Names of the gorillas of the selected level, once it is loaded.
+fn slot_names(settings: &MatchSettings, maps: &Assets<LdtkAsset>) -> Vec<String> {
+    maps.get(&settings.level.map)
+        .and_then(|map| map.project.levels.get(settings.level.level))
+        .map(|level| {
+            level_players(level)
+                .map(|entity| entity.identifier.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn menu_rows(slots: usize) -> Vec<MenuRow> {
+    let mut rows = vec![MenuRow::Level, MenuRow::Rounds];
+    rows.extend((0..slots).map(MenuRow::Slot));
+    rows.extend([
+        MenuRow::Wind,
+        MenuRow::Weapons,
+        MenuRow::Terrain,
+        MenuRow::ReducedMotion,
+        MenuRow::Start,
+    ]);
+    rows
+}
+
+/// Moves between rows with Up and Down, changes the value of a row with Left and Right,
+/// and starts the match with Enter.
+fn navigate_menu(
+    keyboard: Res<Input<KeyCode>>,
+    maps: Res<Assets<LdtkAsset>>,
+    list: Res<LevelList>,
+    mut cursor: ResMut<MenuCursor>,
+    mut settings: ResMut<MatchSettings>,
+    mut camera_settings: ResMut<CameraSettings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let slots = slot_names(&settings, &maps).len();
+    settings.slots.resize(slots, Controller::default());
+    let rows = menu_rows(slots);
+    if keyboard.just_pressed(KeyCode::Down) {
+        cursor.0 = (cursor.0 + 1) % rows.len();
+    }
+    if keyboard.just_pressed(KeyCode::Up) {
+        cursor.0 = (cursor.0 + rows.len() - 1) % rows.len();
+    }
+    cursor.0 = cursor.0.min(rows.len() - 1);
+
+    let step =
+        keyboard.just_pressed(KeyCode::Right) as i32 - keyboard.just_pressed(KeyCode::Left) as i32;
+    if step != 0 {
+        match rows[cursor.0] {
+            MenuRow::Level if !list.0.is_empty() => {
+                let current = list.0.iter().position(|choice| *choice == settings.level);
+                let next = match current {
+                    Some(current) => {
+                        (current as i32 + step).rem_euclid(list.0.len() as i32) as usize
+                    }
+                    None => 0,
+                };
+                settings.level = list.0[next].clone();
+            }
+            MenuRow::Level => {}
+            MenuRow::Rounds => {
+                settings.rounds = settings
+                    .rounds
+                    .saturating_add_signed(step)
+                    .clamp(1, MAX_ROUNDS);
+            }
+            MenuRow::Slot(slot) => {
+                settings.slots[slot] = match settings.slots[slot] {
+                    Controller::Human => Controller::Computer,
+                    Controller::Computer | Controller::External => Controller::Human,
+                };
+            }
+            MenuRow::Wind => settings.wind = !settings.wind,
+            MenuRow::Weapons => {
+                settings.weapons = match settings.weapons {
+                    WeaponSet::Classic => WeaponSet::Full,
+                    WeaponSet::Full => WeaponSet::Classic,
+                };
+            }
+            MenuRow::Terrain => settings.terrain = !settings.terrain,
+            MenuRow::ReducedMotion => {
+                let reduced = camera_settings.reduced_motion();
+                camera_settings.set_reduced_motion(!reduced);
+            }
+            MenuRow::Start => {}
+        }
+    }
+
+    // Players are read from the level, so it has to be loaded first
+    if keyboard.just_pressed(KeyCode::Return) && slots > 0 {
+        info!(settings = ?*settings, "Starting match");
+        next_state.set(AppState::Playing);
+    }
+}
+
+fn update_menu(
+    cursor: Res<MenuCursor>,
+    settings: Res<MatchSettings>,
+    camera_settings: Res<CameraSettings>,
+    maps: Res<Assets<LdtkAsset>>,
+    mut text_query: Query<&mut Text, With<MenuText>>,
+) {
+    let names = slot_names(&settings, &maps);
+    let rows = menu_rows(names.len());
+    let sections: Vec<TextSection> = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let label = match row {
+                MenuRow::Level => format!("Level: < {} >", settings.level.name),
+                MenuRow::Rounds => format!("Rounds: < {} >", settings.rounds),
+                MenuRow::Slot(slot) => {
+                    format!("{}: < {:?} >", names[*slot], settings.controller(*slot))
+                }
+                MenuRow::Wind => format!("Wind: < {} >", if settings.wind { "On" } else { "Off" }),
+                MenuRow::Weapons => format!("Weapons: < {:?} >", settings.weapons),
+                MenuRow::Terrain => format!(
+                    "Board: < {} >",
+                    if settings.terrain {
+                        "Terrain"
+                    } else {
+                        "Bricks"
+                    }
+                ),
+                MenuRow::ReducedMotion => format!(
+                    "Reduced motion: < {} >",
+                    if camera_settings.reduced_motion() {
+                        "On"
+                    } else {
+                        "Off"
+                    }
+                ),
+                MenuRow::Start => "Start".to_string(),
+            };
+            let color = if index == cursor.0 {
+                Color::YELLOW
+            } else {
+                Color::WHITE
+            };
+            TextSection::new(
+                format!("{}\n", label),
+                TextStyle {
+                    font_size: 28.0,
+                    color,
+                    ..default()
+                },
+            )
+        })
+        .collect();
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
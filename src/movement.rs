@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    control::{KinematicCharacterController, KinematicCharacterControllerOutput},
+    plugin::RapierConfiguration,
+};
+
+use crate::{
+    input::{Action, ActionAxis, ActionState},
+    team::PlayerDamageEvent,
+    turn::{ActivePlayer, TurnStarted},
+};
+
+#[derive(Resource, Debug, Clone)]
+pub struct MovementSettings {
+    /// Pixels per second
+    pub walk_speed: f32,
+    /// Pixels a player may walk during their turn
+    pub walk_distance: f32,
+    /// Upwards speed at the start of a jump, in pixels per second
+    pub jump_speed: f32,
+    pub jumps_per_turn: u32,
+    /// Fastest landing that does not hurt, in pixels per second
+    pub safe_fall_speed: f32,
+    /// Damage per pixel per second of landing speed above `safe_fall_speed`
+    pub fall_damage: f32,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        Self {
+            walk_speed: 30.0,
+            walk_distance: 48.0,
+            jump_speed: 60.0,
+            jumps_per_turn: 1,
+            safe_fall_speed: 100.0,
+            fall_damage: 1.0,
+        }
+    }
+}
+
+/// How a gorilla has moved, players only walk and jump during their own turn but fall at
+/// any time.
+#[derive(Component, Debug, Default)]
+pub struct Movement {
+    /// Pixels per second, positive upwards
+    vertical_speed: f32,
+    /// Pixels walked this turn
+    walked: f32,
+    /// Jumps made this turn
+    jumps: u32,
+}
+
+impl Movement {
+    fn landing_damage(&self, settings: &MovementSettings) -> Option<f32> {
+        let landing_speed = -self.vertical_speed;
+        (landing_speed > settings.safe_fall_speed)
+            .then_some((landing_speed - settings.safe_fall_speed) * settings.fall_damage)
+    }
+}
+
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MovementSettings>()
+            .add_systems(Update, (reset_movement, move_players).chain());
+    }
+}
+
+fn reset_movement(mut turn_started: EventReader<TurnStarted>, mut query: Query<&mut Movement>) {
+    for event in turn_started.read() {
+        if let Ok(mut movement) = query.get_mut(event.player) {
+            movement.walked = 0.0;
+            movement.jumps = 0;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn move_players(
+    time: Res<Time>,
+    settings: Res<MovementSettings>,
+    rapier_config: Res<RapierConfiguration>,
+    mut damage_writer: EventWriter<PlayerDamageEvent>,
+    mut query: Query<(
+        Entity,
+        &mut Movement,
+        &mut KinematicCharacterController,
+        Option<&KinematicCharacterControllerOutput>,
+        &ActionState,
+        Has<ActivePlayer>,
+    )>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut movement, mut controller, output, actions, active) in query.iter_mut() {
+        let grounded = output.is_some_and(|output| output.grounded);
+        if grounded {
+            if let Some(damage) = movement.landing_damage(&settings) {
+                damage_writer.send(PlayerDamageEvent {
+                    player: entity,
+                    damage,
+                    shooter: None,
+                    shot: None,
+                });
+            }
+            movement.vertical_speed = 0.0;
+        }
+
+        let mut walk = 0.0;
+        if active {
+            let budget = (settings.walk_distance - movement.walked).max(0.0);
+            walk =
+                (actions.axis(ActionAxis::Move) * settings.walk_speed * dt).clamp(-budget, budget);
+            movement.walked += walk.abs();
+
+            if grounded
+                && actions.just_pressed(Action::Jump)
+                && movement.jumps < settings.jumps_per_turn
+            {
+                movement.jumps += 1;
+                movement.vertical_speed = settings.jump_speed;
+            }
+        }
+
+        movement.vertical_speed += rapier_config.gravity.y * dt;
+        controller.translation = Some(Vec2::new(walk, movement.vertical_speed * dt));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_hard_landings_hurt() {
+        let settings = MovementSettings::default();
+        let mut movement = Movement {
+            vertical_speed: -settings.safe_fall_speed,
+            ..default()
+        };
+        assert_eq!(movement.landing_damage(&settings), None);
+
+        movement.vertical_speed -= 50.0;
+        assert_eq!(
+            movement.landing_damage(&settings),
+            Some(50.0 * settings.fall_damage)
+        );
+    }
+}
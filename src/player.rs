@@ -1,12 +1,24 @@
+use std::collections::HashMap;
+
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_rapier2d::{
-    dynamics::{ExternalImpulse, RigidBody},
-    geometry::{ActiveEvents, Collider},
+    control::KinematicCharacterController, dynamics::RigidBody, geometry::Collider,
 };
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
+    ai::Ai,
+    aim::AimMode,
+    config::{env_value, Config},
+    gym::Bot,
+    input::{Action, ActionState, InputDevice},
     ldtk::{self, convert_coords, LdtkAsset},
+    menu::{AppState, Controller, MatchSettings, WeaponSet},
+    movement::Movement,
+    team::{Health, Team, TeamSettings},
+    turn::{ActivePlayer, TurnClock, TurnEnded, TurnExpiry, TurnOrder, TurnSettings, TurnStarted},
+    weapon::{ShotSpawner, Weapon},
     Players,
 };
 
@@ -14,14 +26,24 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Gun>()
-            .add_systems(Update, (spawn_player, shoot, aim_system, debug_aim));
+        let charge = app
+            .world
+            .get_resource::<Config>()
+            .map(|config| config.charge.clone())
+            .unwrap_or_default();
+        app.add_event::<FireEvent>()
+            .insert_resource(charge)
+            .add_systems(Update, (spawn_player, switch_weapon, shoot, aim_system))
+            .add_systems(OnExit(AppState::Playing), clear_players);
     }
 }
 
 fn spawn_player(
     mut commands: Commands,
     mut players: ResMut<Players>,
+    mut order: ResMut<TurnOrder>,
+    team_settings: Res<TeamSettings>,
+    match_settings: Res<MatchSettings>,
     maps: Res<Assets<LdtkAsset>>,
 ) {
     // Board is already loaded or the map is not loaded yet
@@ -29,68 +51,148 @@ fn spawn_player(
         return;
     }
 
-    eprintln!("Spawning players");
+    info!(level = players.level, "Spawning players");
     let map = maps.get(&players.map).expect("Failed to load map");
     let project = &map.project;
 
-    // Assume we use the first level
-    let level = &project.levels[0];
+    let level = &project.levels[players.level];
     let level_width = level.px_wid as f32;
     let level_height = level.px_hei as f32;
     let level_size = Vec2::new(level_width, level_height);
-    // Use the first tile layer
     let layers = level.layer_instances.as_ref().expect("No layers");
+    let mut slot = 0;
     for layer in layers {
-        spawn_layer(&mut commands, level_size, layer, &mut players);
+        for entity in layer_players(layer) {
+            let player = spawn_entity(
+                &mut commands,
+                level_size,
+                layer.grid_size as f32,
+                entity,
+                &mut order,
+                &team_settings,
+            );
+            let mut gun = Gun::default();
+            // Bananas never run out, everything else is left empty
+            if match_settings.weapons == WeaponSet::Classic {
+                gun.ammo = Weapon::ALL
+                    .into_iter()
+                    .filter(|weapon| *weapon != Weapon::Banana)
+                    .map(|weapon| (weapon, 0))
+                    .collect();
+            }
+            commands.entity(player).insert(gun);
+            match match_settings.controller(slot) {
+                Controller::Human => {}
+                Controller::Computer => {
+                    commands
+                        .entity(player)
+                        .insert(Ai::default())
+                        .insert(AimMode::Classic)
+                        .insert(InputDevice::Computer);
+                }
+                Controller::External => {
+                    commands
+                        .entity(player)
+                        .insert(Bot)
+                        .insert(AimMode::Classic)
+                        .insert(InputDevice::Computer);
+                }
+            }
+            slot += 1;
+        }
     }
 
-    if players.names.len() > 0 {
-        players.current_player = Some(0);
+    players.loaded = true;
+}
+
+/// Removes the gorillas and their shots at the end of a round.
+#[allow(clippy::type_complexity)]
+fn clear_players(mut commands: Commands, query: Query<Entity, Or<(With<Player>, With<Shot>)>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
     }
+}
 
-    players.loaded = true;
+/// The player entities of a layer.
+fn layer_players(layer: &ldtk::LayerInstance) -> impl Iterator<Item = &ldtk::EntityInstance> {
+    layer.entity_instances.iter().filter(|entity| {
+        entity.field_instances.iter().any(|field| {
+            field.identifier == "EntityType"
+                && matches!(&field.value, Some(Value::String(s)) if s == "Player")
+        })
+    })
 }
 
-fn spawn_layer(
+/// The player entities of a level, in the order they are spawned.
+pub fn level_players(level: &ldtk::Level) -> impl Iterator<Item = &ldtk::EntityInstance> {
+    level
+        .layer_instances
+        .iter()
+        .flatten()
+        .flat_map(layer_players)
+}
+
+fn spawn_entity(
     commands: &mut Commands,
     level_size: Vec2,
-    layer: &ldtk::LayerInstance,
-    players: &mut ResMut<Players>,
-) {
-    let grid_size = layer.grid_size as f32;
-
-    // Spawn entities
-    for entity in &layer.entity_instances {
-        let player = entity.field_instances.iter().find(|field| {
-            if field.identifier == "EntityType" {
-                match &field.value {
-                    Some(Value::String(s)) if s == "Player" => true,
-                    _ => false,
-                }
-            } else {
-                false
-            }
-        });
-
-        if player.is_some() {
-            players.names.push(entity.identifier.clone());
-            let position = convert_coords(&entity.px, grid_size, level_size);
-            create_player(
-                commands,
-                position,
-                Vec2::new(10.0, 10.0),
-                entity.identifier.clone(),
-            );
-        }
-    }
+    grid_size: f32,
+    entity: &ldtk::EntityInstance,
+    order: &mut TurnOrder,
+    team_settings: &TeamSettings,
+) -> Entity {
+    let position = convert_coords(&entity.px, grid_size, level_size);
+    let aim_mode = entity
+        .field_instances
+        .iter()
+        .find(|field| field.identifier == "AimMode")
+        .and_then(|field| field.value.as_ref()?.as_str()?.parse().ok())
+        .unwrap_or_default();
+    let team = entity
+        .field_instances
+        .iter()
+        .find(|field| field.identifier == "Team")
+        .and_then(|field| match field.value.as_ref()? {
+            Value::String(team) => Some(team.clone()),
+            Value::Number(team) => Some(team.to_string()),
+            _ => None,
+        })
+        .unwrap_or_else(|| entity.identifier.clone());
+    let player = create_player(
+        commands,
+        position,
+        Vec2::splat(PLAYER_SIZE),
+        entity.identifier.clone(),
+        aim_mode,
+    );
+    commands
+        .entity(player)
+        .insert(Team(team.clone()))
+        .insert(Health(team_settings.player_health));
+    order.add(Team(team), player);
+    player
 }
 
+/// Side of a gorilla in pixels
+const PLAYER_SIZE: f32 = 10.0;
+
 #[derive(Component, Debug)]
 pub struct Player {
     name: String,
 }
 
-fn create_player(commands: &mut Commands, position: Vec2, size: Vec2, name: String) {
+impl Player {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+fn create_player(
+    commands: &mut Commands,
+    position: Vec2,
+    size: Vec2,
+    name: String,
+    aim_mode: AimMode,
+) -> Entity {
     commands
         .spawn(SpriteBundle {
             sprite: Sprite {
@@ -101,98 +203,259 @@ fn create_player(commands: &mut Commands, position: Vec2, size: Vec2, name: Stri
             transform: Transform::from_xyz(position.x, position.y, 0.0),
             ..default()
         })
-        .insert(Player { name });
+        .insert(Player { name })
+        .insert(RigidBody::KinematicPositionBased)
+        .insert(Collider::cuboid(size.x / 2.0, size.y / 2.0))
+        .insert(KinematicCharacterController::default())
+        .insert(Movement::default())
+        .insert(aim_mode)
+        .insert(InputDevice::default())
+        .insert(ActionState::default())
+        .id()
 }
 
 #[derive(Debug, Component)]
-pub struct Shot;
-
-fn spawn_shot(commands: &mut Commands, position: Vec2, impulse: Vec2) {
-    let foo = commands
-        .spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2::splat(8.0)),
-                    color: Color::rgb(0.8, 0.0, 0.0),
-                    ..Default::default()
-                },
-                transform: Transform::from_xyz(position.x, position.y, 0.0),
-                ..Default::default()
-            },
-            Shot,
-        ))
-        .insert(RigidBody::Dynamic)
-        .insert(Collider::cuboid(4.0, 4.0))
-        // .insert(Collider::cuboid(8.0, 8.0))
-        .insert(ExternalImpulse {
-            impulse,
-            ..Default::default()
-        })
-        .insert(ActiveEvents::COLLISION_EVENTS)
-        // .insert(CollidingEntities::default())
-        .id();
-    dbg!(foo);
+pub struct Shot {
+    /// The player who threw it
+    pub shooter: Entity,
+    pub weapon: Weapon,
+}
+
+/// Aim and weapons of a player, kept between their turns.
+#[derive(Debug, Component)]
+pub struct Gun {
+    pub force: f32,
+    pub target: Vec2,
+    /// Degrees above the horizon, used by the keyboard aim modes
+    pub angle: f32,
+    /// Seconds the trigger has been held
+    charge_time: f32,
+    /// Force of the previous shot
+    pub last_force: f32,
+    pub weapon: Weapon,
+    /// Shots left of the weapons that can run out
+    pub ammo: HashMap<Weapon, u32>,
+}
+
+impl Default for Gun {
+    fn default() -> Self {
+        Self {
+            force: 0.0,
+            target: Vec2::ZERO,
+            angle: 45.0,
+            charge_time: 0.0,
+            last_force: 0.0,
+            weapon: Weapon::Banana,
+            ammo: Weapon::ALL
+                .into_iter()
+                .filter_map(|weapon| Some((weapon, weapon.starting_ammo()?)))
+                .collect(),
+        }
+    }
 }
 
-#[derive(Debug, Resource, Default)]
-struct Gun {
-    force: f32,
-    target: Vec2,
+impl Gun {
+    pub fn has_ammo(&self, weapon: Weapon) -> bool {
+        !matches!(self.ammo.get(&weapon), Some(0))
+    }
+
+    /// Selects the next weapon that has shots left.
+    pub fn next_weapon(&mut self) {
+        let current = Weapon::ALL
+            .iter()
+            .position(|weapon| *weapon == self.weapon)
+            .unwrap_or(0);
+        for offset in 1..=Weapon::ALL.len() {
+            let weapon = Weapon::ALL[(current + offset) % Weapon::ALL.len()];
+            if self.has_ammo(weapon) {
+                self.weapon = weapon;
+                return;
+            }
+        }
+    }
+
+    fn take_ammo(&mut self) {
+        if let Some(ammo) = self.ammo.get_mut(&self.weapon) {
+            *ammo = ammo.saturating_sub(1);
+        }
+        if !self.has_ammo(self.weapon) {
+            self.next_weapon();
+        }
+    }
 }
 
+/// Fires the gun with its current force, for input modes that do not charge with Space.
+#[derive(Event, Debug)]
+pub struct FireEvent;
+
+/// How the force of a shot builds up while the trigger is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChargeCurve {
+    Linear,
+    /// Slow at first, then faster towards full force
+    EaseIn,
+    /// Swings between no and full force until released, like a power meter
+    Oscillating,
+}
+
+impl ChargeCurve {
+    /// Fraction of full force after charging for `t` charge periods.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            ChargeCurve::Linear => t.clamp(0.0, 1.0),
+            ChargeCurve::EaseIn => t.clamp(0.0, 1.0).powi(2),
+            ChargeCurve::Oscillating => 1.0 - ((t.max(0.0) % 2.0) - 1.0).abs(),
+        }
+    }
+}
+
+impl std::str::FromStr for ChargeCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(ChargeCurve::Linear),
+            "ease-in" => Ok(ChargeCurve::EaseIn),
+            "oscillating" => Ok(ChargeCurve::Oscillating),
+            _ => Err(format!("Unknown charge curve: {}", s)),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChargeSettings {
+    pub max_force: f32,
+    /// Seconds to charge up to full force
+    pub charge_time: f32,
+    pub curve: ChargeCurve,
+}
+
+impl Default for ChargeSettings {
+    fn default() -> Self {
+        Self {
+            max_force: 200.0,
+            charge_time: 2.0,
+            curve: ChargeCurve::Linear,
+        }
+    }
+}
+
+impl ChargeSettings {
+    /// Overridden by `GORILLAS_MAX_FORCE` and `GORILLAS_CHARGE_CURVE`.
+    pub fn apply_env(&mut self) {
+        if let Some(max_force) = env_value("GORILLAS_MAX_FORCE") {
+            self.max_force = max_force;
+        }
+        if let Some(curve) = env_value("GORILLAS_CHARGE_CURVE") {
+            self.curve = curve;
+        }
+    }
+
+    fn force(&self, charge_time: f32) -> f32 {
+        self.curve.apply(charge_time / self.charge_time) * self.max_force
+    }
+}
+
+fn switch_weapon(mut q_player: Query<(&mut Gun, &ActionState, &Player), With<ActivePlayer>>) {
+    if let Ok((mut gun, actions, player)) = q_player.get_single_mut() {
+        if actions.just_pressed(Action::SwitchWeapon) {
+            gun.next_weapon();
+            debug!(player = player.name, weapon = ?gun.weapon, "Switched weapon");
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn shoot(
     mut commands: Commands,
-    mut gun: ResMut<Gun>,
-    mut players: ResMut<Players>,
-    keyboard: Res<Input<KeyCode>>,
-    q_player: Query<(&GlobalTransform, &Player)>,
+    clock: Res<TurnClock>,
+    time: Res<Time>,
+    charge: Res<ChargeSettings>,
+    turn: Res<TurnSettings>,
+    mut fire_events: EventReader<FireEvent>,
+    mut turn_ended: EventWriter<TurnEnded>,
+    shots: ShotSpawner,
+    mut q_player: Query<
+        (Entity, &GlobalTransform, &AimMode, &ActionState, &mut Gun),
+        With<ActivePlayer>,
+    >,
 ) {
-    if keyboard.just_released(KeyCode::Space) {
-        // Find the current player
-        let player = q_player.iter().find(|(_, player)| {
-            if let Some(current_player) = &players.current_player {
-                player.name == players.names[*current_player]
-            } else {
-                false
-            }
-        });
+    let fired = fire_events.read().count() > 0;
+    let Ok((entity, transform, mode, actions, mut gun)) = q_player.get_single_mut() else {
+        return;
+    };
+    // Classic players type their velocity instead of charging
+    let charging = *mode != AimMode::Classic;
 
-        if let Some((transform, _)) = player {
+    let expired = clock.expired();
+    let fire = fired
+        || (charging && actions.just_released(Action::Charge))
+        || (expired && turn.on_expiry == TurnExpiry::AutoFire);
+    if fire || expired {
+        if fire {
             let player_position = transform.translation().truncate();
 
             let direction = gun.target - player_position;
 
-            eprintln!("Shooting");
-            let impulse = direction.normalize() * gun.force;
-            eprintln!("Impulse: {:?}", impulse);
-            spawn_shot(&mut commands, player_position, impulse);
-
-            // Reset gun
-            gun.force = 0.0;
-            // Swap player
-            let player_index = players.current_player.unwrap_or(0);
-            let player_index = (player_index + 1) % players.names.len();
-            players.current_player = Some(player_index);
+            let impulse = direction.normalize_or_zero() * gun.force;
+            // Start outside the thrower so the shot does not hit them right away
+            let offset =
+                (PLAYER_SIZE + shots.stats(gun.weapon).size) * std::f32::consts::FRAC_1_SQRT_2;
+            let position = player_position + direction.normalize_or_zero() * offset;
+            let shot = shots.spawn(&mut commands, entity, position, impulse, gun.weapon);
+            debug!(?shot, ?impulse, weapon = ?gun.weapon, "Shot");
+            gun.last_force = gun.force;
+            gun.take_ammo();
         }
-    }
-    if keyboard.pressed(KeyCode::Space) {
-        gun.force += 1.0;
+
+        // Reset gun
+        gun.force = 0.0;
+        gun.charge_time = 0.0;
+        turn_ended.send(TurnEnded { player: entity });
+    } else if charging && actions.pressed(Action::Charge) {
+        gun.charge_time += time.delta_seconds();
+        gun.force = charge.force(gun.charge_time);
     }
 }
 
 fn aim_system(
-    mut gun: ResMut<Gun>,
+    mut turn_started: EventReader<TurnStarted>,
+    mut q_player: Query<(Entity, &AimMode, &InputDevice, &mut Gun), With<ActivePlayer>>,
     // query to get the window (so we can read the current cursor position)
-    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
     // query to get camera transform
     q_camera: Query<(&Camera, &GlobalTransform)>,
 ) {
+    // Only players aiming with the mouse follow the cursor
+    let Ok((entity, mode, device, mut gun)) = q_player.get_single_mut() else {
+        return;
+    };
+    let new_turn = turn_started
+        .read()
+        .filter(|event| event.player == entity)
+        .count()
+        > 0;
+    if *mode != AimMode::Mouse || *device != InputDevice::KeyboardMouse {
+        return;
+    }
+
     // get the camera info and transform
     // assuming there is exactly one main camera entity, so Query::single() is OK
     let (camera, camera_transform) = q_camera.single();
 
     // There is only one primary window, so we can similarly get it from the query:
-    let window = q_window.single();
+    let mut window = q_window.single_mut();
+
+    // Put the cursor back where the player aimed last turn, like the original game
+    if new_turn {
+        let cursor = camera.world_to_viewport(camera_transform, gun.target.extend(0.0));
+        if gun.last_force > 0.0 && cursor.is_some() {
+            window.set_cursor_position(cursor);
+        }
+        return;
+    }
 
     // check if the cursor is inside the window and get its position
     // then, ask bevy to convert into world coordinates, and truncate to discard Z
@@ -205,21 +468,47 @@ fn aim_system(
     }
 }
 
-fn debug_aim(
-    player_q: Query<(&GlobalTransform, &Player)>,
-    gun: Res<Gun>,
-    players: Res<Players>,
-    mut gizmo: Gizmos,
-) {
-    let player = player_q.iter().find(|(_, player)| {
-        if let Some(current_player) = &players.current_player {
-            player.name == players.names[*current_player]
-        } else {
-            false
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charge_is_capped() {
+        let settings = ChargeSettings::default();
+        for curve in [
+            ChargeCurve::Linear,
+            ChargeCurve::EaseIn,
+            ChargeCurve::Oscillating,
+        ] {
+            let settings = ChargeSettings {
+                curve,
+                ..settings.clone()
+            };
+            for step in 0..100 {
+                let force = settings.force(step as f32 * 0.1);
+                assert!((0.0..=settings.max_force).contains(&force));
+            }
         }
-    });
-    if let Some((transform, _)) = player {
-        let translation = transform.translation().truncate();
-        gizmo.line_2d(translation, gun.target, Color::rgb(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn empty_weapons_are_skipped() {
+        let mut gun = Gun::default();
+        gun.next_weapon();
+        assert_eq!(gun.weapon, Weapon::Bunch);
+        gun.take_ammo();
+        gun.take_ammo();
+        assert_eq!(gun.weapon, Weapon::Banana);
+        gun.next_weapon();
+        assert_eq!(gun.weapon, Weapon::Banana);
+    }
+
+    #[test]
+    fn oscillating_charge_swings_back() {
+        let curve = ChargeCurve::Oscillating;
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert_eq!(curve.apply(1.0), 1.0);
+        assert_eq!(curve.apply(1.5), 0.5);
+        assert_eq!(curve.apply(2.0), 0.0);
     }
 }
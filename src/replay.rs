@@ -0,0 +1,210 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::ExternalImpulse;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aim::{AimMode, AIM_DISTANCE},
+    input::InputDevice,
+    ldtk::LdtkAsset,
+    menu::{AppState, MatchSettings},
+    player::{FireEvent, Gun, Player, Shot},
+    turn::{ActivePlayer, TurnStarted},
+    weapon::{Weapon, Wind},
+};
+
+/// Seconds a replayed player waits before throwing
+const REPLAY_DELAY: f32 = 0.5;
+
+/// A shot as it left the hand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayShot {
+    pub player: String,
+    pub impulse: [f32; 2],
+    pub weapon: Weapon,
+    /// Wind of the round, which is picked at random
+    #[serde(default)]
+    pub wind: f32,
+}
+
+/// The level and shots of a game, in the order they were thrown.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    /// LDtk project played, relative to the assets folder, `None` for a random skyline
+    #[serde(default)]
+    pub level: Option<String>,
+    /// Identifier of the level in the project
+    #[serde(default)]
+    pub level_id: Option<String>,
+    /// Seed of the random skyline played
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Played on pixel terrain rather than bricks
+    #[serde(default)]
+    pub terrain: bool,
+    pub shots: Vec<ReplayShot>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+        let writer = BufWriter::new(std::fs::File::create(path)?);
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+}
+
+/// Shots left to play back for each player.
+#[derive(Resource, Debug, Default)]
+struct Playback {
+    shots: HashMap<String, VecDeque<ReplayShot>>,
+    delay: Timer,
+}
+
+/// Shots thrown so far, and the file they are written to.
+#[derive(Resource, Debug)]
+struct Recording {
+    replay: Replay,
+    path: PathBuf,
+}
+
+/// Plays the shots of a replay file back instead of reading input, and records the shots
+/// thrown to another file.
+pub struct ReplayPlugin {
+    pub replay: Option<Replay>,
+    pub record: Option<PathBuf>,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(replay) = &self.replay {
+            let mut playback = Playback {
+                delay: Timer::from_seconds(REPLAY_DELAY, TimerMode::Once),
+                ..default()
+            };
+            for shot in &replay.shots {
+                playback
+                    .shots
+                    .entry(shot.player.clone())
+                    .or_default()
+                    .push_back(shot.clone());
+            }
+            app.insert_resource(playback)
+                .add_systems(Update, (take_over_players, play_back).chain());
+        }
+        if let Some(path) = &self.record {
+            app.insert_resource(Recording {
+                replay: Replay::default(),
+                path: path.clone(),
+            })
+            .add_systems(OnExit(AppState::Menu), record_level)
+            .add_systems(Update, record_shots);
+        }
+    }
+}
+
+/// Players with shots in the replay no longer take input.
+fn take_over_players(
+    mut commands: Commands,
+    playback: Res<Playback>,
+    player_query: Query<(Entity, &Player), Added<Player>>,
+) {
+    for (entity, player) in player_query.iter() {
+        if playback.shots.contains_key(player.name()) {
+            commands
+                .entity(entity)
+                .insert(AimMode::Classic)
+                .insert(InputDevice::Computer);
+        }
+    }
+}
+
+fn play_back(
+    time: Res<Time>,
+    mut playback: ResMut<Playback>,
+    mut wind: ResMut<Wind>,
+    mut turn_started: EventReader<TurnStarted>,
+    mut fire: EventWriter<FireEvent>,
+    mut player_query: Query<(&GlobalTransform, &Player, &mut Gun), With<ActivePlayer>>,
+) {
+    if turn_started.read().count() > 0 {
+        playback.delay.reset();
+    }
+    if !playback.delay.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Ok((transform, player, mut gun)) = player_query.get_single_mut() else {
+        return;
+    };
+    let Some(shot) = playback
+        .shots
+        .get_mut(player.name())
+        .and_then(|shots| shots.pop_front())
+    else {
+        return;
+    };
+
+    let impulse = Vec2::from(shot.impulse);
+    gun.weapon = shot.weapon;
+    gun.target = transform.translation().truncate() + impulse.normalize_or_zero() * AIM_DISTANCE;
+    gun.force = impulse.length();
+    wind.0 = shot.wind;
+    fire.send(FireEvent);
+}
+
+/// Notes the level picked, so the replay can be played back on it.
+fn record_level(
+    mut recording: ResMut<Recording>,
+    settings: Res<MatchSettings>,
+    asset_server: Res<AssetServer>,
+    maps: Res<Assets<LdtkAsset>>,
+) {
+    let level = &settings.level;
+    let replay = &mut recording.replay;
+    // Random skylines are generated, not loaded
+    replay.level = asset_server
+        .get_path(level.map.id())
+        .map(|path| path.path().to_string_lossy().into_owned())
+        .filter(|_| level.seed.is_none());
+    replay.level_id = maps
+        .get(&level.map)
+        .and_then(|map| map.project.levels.get(level.level))
+        .map(|ldtk_level| ldtk_level.identifier.clone());
+    replay.seed = level.seed;
+    replay.terrain = settings.terrain;
+}
+
+fn record_shots(
+    mut recording: ResMut<Recording>,
+    wind: Res<Wind>,
+    shot_query: Query<(&Shot, &ExternalImpulse), Added<Shot>>,
+    player_query: Query<&Player>,
+) {
+    let mut recorded = false;
+    for (shot, impulse) in shot_query.iter() {
+        let Ok(player) = player_query.get(shot.shooter) else {
+            continue;
+        };
+        recording.replay.shots.push(ReplayShot {
+            player: player.name().to_string(),
+            impulse: impulse.impulse.into(),
+            weapon: shot.weapon,
+            wind: wind.0,
+        });
+        recorded = true;
+    }
+    // Written after every shot, so nothing is lost when the game is closed
+    if recorded {
+        if let Err(err) = recording.replay.save(&recording.path) {
+            error!(path = %recording.path.display(), %err, "Failed to write replay");
+        }
+    }
+}
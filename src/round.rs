@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    menu::{AppState, MatchSettings},
+    player::Player,
+    team::{PlayerDamageEvent, PlayerEliminated, RoundOver, Team},
+};
+
+/// Seconds the board stays up after a round is won, before the scores are shown
+const ROUND_END_DELAY: f32 = 3.0;
+
+#[derive(Resource, Debug, Clone)]
+pub struct ScoreSettings {
+    /// Points for knocking out an opponent
+    pub knockout: f32,
+    /// Points per point of damage dealt to opponents
+    pub damage: f32,
+    /// Points for hitting yourself or a teammate, usually negative
+    pub self_hit: f32,
+}
+
+impl Default for ScoreSettings {
+    fn default() -> Self {
+        Self {
+            knockout: 100.0,
+            damage: 1.0,
+            self_hit: -50.0,
+        }
+    }
+}
+
+/// What a gorilla has done so far in the match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Score {
+    pub team: Option<Team>,
+    pub knockouts: u32,
+    pub damage: f32,
+    pub self_hits: u32,
+    pub points: f32,
+}
+
+/// Scores of the match being played. Gorillas are respawned every round, so they are kept
+/// by name.
+#[derive(Resource, Debug, Default)]
+pub struct MatchScore {
+    /// Starting at 1
+    pub round: u32,
+    pub players: HashMap<String, Score>,
+    /// Winner of each round played, `None` for a draw
+    pub winners: Vec<Option<Team>>,
+}
+
+impl MatchScore {
+    fn player(&mut self, name: &str, team: &Team) -> &mut Score {
+        let score = self.players.entry(name.to_string()).or_default();
+        score.team = Some(team.clone());
+        score
+    }
+
+    /// Rounds won and points of each team, best first.
+    pub fn standings(&self) -> Vec<(Team, u32, f32)> {
+        fn entry<'a>(
+            standings: &'a mut Vec<(Team, u32, f32)>,
+            team: &Team,
+        ) -> &'a mut (Team, u32, f32) {
+            match standings.iter().position(|(other, _, _)| other == team) {
+                Some(index) => &mut standings[index],
+                None => {
+                    standings.push((team.clone(), 0, 0.0));
+                    standings.last_mut().unwrap()
+                }
+            }
+        }
+
+        let mut standings = Vec::new();
+        for team in self.winners.iter().flatten() {
+            entry(&mut standings, team).1 += 1;
+        }
+        for score in self.players.values() {
+            if let Some(team) = &score.team {
+                entry(&mut standings, team).2 += score.points;
+            }
+        }
+        standings.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.total_cmp(&a.2)));
+        standings
+    }
+
+    /// The team with the most rounds won, then the most points. `None` for a draw.
+    pub fn winner(&self) -> Option<Team> {
+        let standings = self.standings();
+        match standings.as_slice() {
+            [] => None,
+            [(team, ..)] => Some(team.clone()),
+            [(team, wins, points), (_, next_wins, next_points), ..] => {
+                (wins != next_wins || points != next_points).then(|| team.clone())
+            }
+        }
+    }
+}
+
+/// Counts down from the end of a round to the summary. Turns still go on meanwhile.
+#[derive(Resource, Debug)]
+pub struct RoundEnding(Timer);
+
+#[derive(Component)]
+struct Summary;
+
+pub struct RoundPlugin;
+
+impl Plugin for RoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScoreSettings>()
+            .init_resource::<MatchScore>()
+            .add_systems(OnExit(AppState::Menu), start_match)
+            .add_systems(
+                Update,
+                (score_damage, score_knockouts, end_round).run_if(in_state(AppState::Playing)),
+            )
+            .add_systems(OnEnter(AppState::RoundOver), spawn_summary)
+            .add_systems(OnExit(AppState::RoundOver), despawn_summary)
+            .add_systems(Update, next_round.run_if(in_state(AppState::RoundOver)));
+    }
+}
+
+fn start_match(mut score: ResMut<MatchScore>) {
+    *score = MatchScore {
+        round: 1,
+        ..default()
+    };
+}
+
+fn score_damage(
+    settings: Res<ScoreSettings>,
+    mut score: ResMut<MatchScore>,
+    mut damage_reader: EventReader<PlayerDamageEvent>,
+    player_query: Query<(&Player, &Team)>,
+) {
+    for event in damage_reader.read() {
+        let Some(shooter) = event.shooter else {
+            continue;
+        };
+        let (Ok((_, victim_team)), Ok((shooter, shooter_team))) =
+            (player_query.get(event.player), player_query.get(shooter))
+        else {
+            continue;
+        };
+        let score = score.player(shooter.name(), shooter_team);
+        if victim_team == shooter_team {
+            score.self_hits += 1;
+            score.points += settings.self_hit;
+        } else {
+            score.damage += event.damage;
+            score.points += event.damage * settings.damage;
+        }
+    }
+}
+
+fn score_knockouts(
+    settings: Res<ScoreSettings>,
+    mut score: ResMut<MatchScore>,
+    mut eliminated_reader: EventReader<PlayerEliminated>,
+    player_query: Query<(&Player, &Team)>,
+) {
+    for event in eliminated_reader.read() {
+        let Some(Ok((shooter, shooter_team))) =
+            event.shooter.map(|shooter| player_query.get(shooter))
+        else {
+            continue;
+        };
+        if *shooter_team != event.team {
+            let score = score.player(shooter.name(), shooter_team);
+            score.knockouts += 1;
+            score.points += settings.knockout;
+        }
+    }
+}
+
+/// Records the winner of the round and shows the scores a little later.
+fn end_round(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut score: ResMut<MatchScore>,
+    mut round_over_reader: EventReader<RoundOver>,
+    ending: Option<ResMut<RoundEnding>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    // Only the first result of a round counts
+    if let Some(event) = round_over_reader.read().next() {
+        if ending.is_none() {
+            score.winners.push(event.winner.clone());
+            commands.insert_resource(RoundEnding(Timer::from_seconds(
+                ROUND_END_DELAY,
+                TimerMode::Once,
+            )));
+        }
+    }
+    let Some(mut ending) = ending else {
+        return;
+    };
+    if ending.0.tick(time.delta()).finished() {
+        commands.remove_resource::<RoundEnding>();
+        next_state.set(AppState::RoundOver);
+    }
+}
+
+fn summary_text(score: &MatchScore, rounds: u32) -> String {
+    let mut text = format!("Round {} of {}\n", score.round, rounds);
+    match score.winners.last() {
+        Some(Some(team)) => text += &format!("{} won the round\n\n", team.0),
+        _ => text += "The round was a draw\n\n",
+    }
+
+    let mut players: Vec<_> = score.players.iter().collect();
+    players.sort_by_key(|(name, _)| *name);
+    for (name, player) in players {
+        text += &format!(
+            "{}: {} knockouts, {:.0} damage, {} self-hits, {:.0} points\n",
+            name, player.knockouts, player.damage, player.self_hits, player.points
+        );
+    }
+    text += "\n";
+    for (team, wins, points) in score.standings() {
+        text += &format!("{}: {} rounds, {:.0} points\n", team.0, wins, points);
+    }
+
+    if score.round >= rounds {
+        match score.winner() {
+            Some(team) => text += &format!("\n{} wins the match!\n", team.0),
+            None => text += "\nThe match is a draw!\n",
+        }
+        text += "Press Enter to return to the menu";
+    } else {
+        text += "\nPress Enter for the next round";
+    }
+    text
+}
+
+fn spawn_summary(mut commands: Commands, score: Res<MatchScore>, settings: Res<MatchSettings>) {
+    let text = summary_text(&score, settings.rounds);
+    info!(round = score.round, winner = ?score.winners.last(), "Round over\n{}", text);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.0, 0.0, 0.4).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            Summary,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn despawn_summary(mut commands: Commands, query: Query<Entity, With<Summary>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn next_round(
+    keyboard: Res<Input<KeyCode>>,
+    settings: Res<MatchSettings>,
+    mut score: ResMut<MatchScore>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if !keyboard.just_pressed(KeyCode::Return) && !settings.unattended {
+        return;
+    }
+    if score.round >= settings.rounds && settings.unattended {
+        exit.send(AppExit);
+    } else if score.round >= settings.rounds {
+        next_state.set(AppState::Menu);
+    } else {
+        score.round += 1;
+        next_state.set(AppState::Playing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_won_beat_points() {
+        let [a, b] = ["A", "B"].map(|team| Team(team.to_string()));
+        let mut score = MatchScore::default();
+        score.player("Player1", &a).points = 300.0;
+        score.player("Player2", &b).points = 100.0;
+        score.winners = vec![Some(b.clone()), None];
+        assert_eq!(score.winner(), Some(b.clone()));
+
+        score.winners.push(Some(a.clone()));
+        assert_eq!(score.winner(), Some(a));
+    }
+}
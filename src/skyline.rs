@@ -0,0 +1,275 @@
+use std::ops::RangeInclusive;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::Value;
+
+use crate::ldtk::{self, EntityInstance, FieldInstance, LayerInstance, Project};
+
+/// LDtk project providing the definitions (layers, tileset, player entities) used by
+/// generated skylines. Its single level is replaced by the generated one.
+const TEMPLATE: &str = include_str!("../assets/skyline.ldtk");
+
+/// Tile tagged `Destructable` in the template tileset.
+pub const BRICK_TILE: i64 = 0;
+/// Tile tagged `Window` in the template tileset.
+pub const WINDOW_TILE: i64 = 1;
+
+#[derive(Debug, Clone)]
+pub struct SkylineSettings {
+    pub seed: u64,
+    /// Level width in cells
+    pub columns: i64,
+    /// Level height in cells
+    pub rows: i64,
+    /// Building width in cells
+    pub building_width: RangeInclusive<i64>,
+    /// Building height in cells
+    pub building_height: RangeInclusive<i64>,
+    /// Probability that a window slot holds a window rather than a brick
+    pub window_chance: f64,
+}
+
+impl Default for SkylineSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            columns: 28,
+            rows: 16,
+            building_width: 3..=6,
+            building_height: 3..=11,
+            window_chance: 0.7,
+        }
+    }
+}
+
+impl SkylineSettings {
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Settings fitted to the level size, so there are at least two buildings, one for
+    /// each gorilla, and room above the highest one.
+    fn validated(&self) -> Self {
+        let columns = self.columns.max(2);
+        let rows = self.rows.max(3);
+        let max_width = (*self.building_width.end()).clamp(1, columns / 2);
+        let min_width = (*self.building_width.start()).clamp(1, max_width);
+        let max_height = (*self.building_height.end()).clamp(1, rows - 2);
+        let min_height = (*self.building_height.start()).clamp(1, max_height);
+        Self {
+            seed: self.seed,
+            columns,
+            rows,
+            building_width: min_width..=max_width,
+            building_height: min_height..=max_height,
+            window_chance: self.window_chance.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Generates a project with a single level: a row of buildings made of bricks and windows,
+/// with a gorilla standing on the roof of the second building from each side.
+pub fn generate(settings: &SkylineSettings) -> Project {
+    let settings = &settings.validated();
+    let mut project: Project = serde_json::from_str(TEMPLATE).expect("Invalid skyline template");
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+
+    // Skyline as (first column, width, height) of each building
+    let mut buildings = Vec::new();
+    let mut column = 0;
+    while column < settings.columns {
+        let width = rng
+            .gen_range(settings.building_width.clone())
+            .min(settings.columns - column);
+        let height = rng.gen_range(settings.building_height.clone());
+        buildings.push((column, width, height));
+        column += width;
+    }
+
+    let tileset = &project.defs.tilesets[0];
+    let tile_size = tileset.tile_grid_size;
+
+    let mut tiles = Vec::new();
+    for &(first_column, width, height) in &buildings {
+        for x in 0..width {
+            for y in 0..height {
+                // Windows are laid out on every other cell, away from the walls and the roof
+                let window_slot = x % 2 == 1 && x < width - 1 && y % 2 == 1 && y < height - 1;
+                let t = if window_slot && rng.gen_bool(settings.window_chance) {
+                    WINDOW_TILE
+                } else {
+                    BRICK_TILE
+                };
+                let cell = [first_column + x, settings.rows - 1 - y];
+                tiles.push(ldtk::tile_instance(tileset, t, cell, settings.columns));
+            }
+        }
+    }
+
+    // Classic placement: second building from the left and from the right
+    let (left, right) = if buildings.len() >= 4 {
+        (1, buildings.len() - 2)
+    } else {
+        (0, buildings.len() - 1)
+    };
+    let mut entities = Vec::new();
+    for (identifier, building) in [("Player1", buildings[left]), ("Player2", buildings[right])] {
+        let (first_column, width, height) = building;
+        let cell = [first_column + width / 2, settings.rows - 1 - height];
+        entities.push(player_instance(
+            &project, identifier, cell, tile_size, &mut rng,
+        ));
+    }
+
+    let level = &mut project.levels[0];
+    level.identifier = format!("Skyline_{}", settings.seed);
+    level.iid = random_iid(&mut rng);
+    level.px_wid = settings.columns * tile_size;
+    level.px_hei = settings.rows * tile_size;
+    let layers = level
+        .layer_instances
+        .as_mut()
+        .expect("Skyline template has no layers");
+    for layer in layers.iter_mut() {
+        layer.c_wid = settings.columns;
+        layer.c_hei = settings.rows;
+        layer.iid = random_iid(&mut rng);
+    }
+    fill_layer(layers, "Tiles", |layer| layer.grid_tiles = tiles);
+    fill_layer(layers, "Entities", |layer| {
+        layer.entity_instances = entities
+    });
+
+    project
+}
+
+fn fill_layer(
+    layers: &mut [LayerInstance],
+    layer_type: &str,
+    fill: impl FnOnce(&mut LayerInstance),
+) {
+    let layer = layers
+        .iter_mut()
+        .find(|layer| layer.layer_instance_type == layer_type)
+        .unwrap_or_else(|| panic!("Skyline template has no {} layer", layer_type));
+    fill(layer);
+}
+
+fn player_instance(
+    project: &Project,
+    identifier: &str,
+    cell: [i64; 2],
+    grid_size: i64,
+    rng: &mut StdRng,
+) -> EntityInstance {
+    let definition = project
+        .defs
+        .entities
+        .iter()
+        .find(|entity| entity.identifier == identifier)
+        .unwrap_or_else(|| panic!("Skyline template has no {} entity", identifier));
+
+    let field_instances = definition
+        .field_defs
+        .iter()
+        .filter(|field| field.identifier == "EntityType")
+        .map(|field| FieldInstance {
+            identifier: field.identifier.clone(),
+            tile: None,
+            field_instance_type: field.field_definition_type.clone(),
+            value: Some(Value::String("Player".to_string())),
+            def_uid: field.uid,
+            real_editor_values: Vec::new(),
+        })
+        .collect();
+
+    let px = vec![cell[0] * grid_size, cell[1] * grid_size];
+    EntityInstance {
+        grid: cell.to_vec(),
+        identifier: definition.identifier.clone(),
+        pivot: vec![definition.pivot_x, definition.pivot_y],
+        smart_color: definition.color.clone(),
+        tags: definition.tags.clone(),
+        tile: None,
+        world_x: px[0],
+        world_y: px[1],
+        def_uid: definition.uid,
+        field_instances,
+        height: definition.height,
+        iid: random_iid(rng),
+        px,
+        width: definition.width,
+    }
+}
+
+/// Random UUID in the format LDtk uses for instance identifiers.
+fn random_iid(rng: &mut impl Rng) -> String {
+    let bytes: [u8; 16] = rng.gen();
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiles(project: &Project) -> Vec<(Vec<i64>, i64)> {
+        project.levels[0]
+            .layer_instances
+            .iter()
+            .flatten()
+            .flat_map(|layer| &layer.grid_tiles)
+            .map(|tile| (tile.px.clone(), tile.t))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_skyline() {
+        let a = generate(&SkylineSettings::with_seed(42));
+        let b = generate(&SkylineSettings::with_seed(42));
+        let c = generate(&SkylineSettings::with_seed(43));
+        assert_eq!(tiles(&a), tiles(&b));
+        assert_ne!(tiles(&a), tiles(&c));
+    }
+
+    #[test]
+    fn players_stand_on_roofs() {
+        let settings = SkylineSettings::with_seed(7);
+        let project = generate(&settings);
+        let layers = project.levels[0].layer_instances.as_ref().unwrap();
+        let tiles = tiles(&project);
+        let players: Vec<_> = layers.iter().flat_map(|l| &l.entity_instances).collect();
+        assert_eq!(players.len(), 2);
+        for player in players {
+            let below = vec![player.px[0], player.px[1] + 16];
+            assert!(tiles.iter().any(|(px, _)| *px == below));
+            assert!(!tiles.iter().any(|(px, _)| *px == player.px));
+        }
+    }
+
+    #[test]
+    fn narrow_levels_have_two_buildings() {
+        for columns in [0, 1, 2, 3, 5] {
+            let settings = SkylineSettings {
+                columns,
+                rows: 2,
+                ..SkylineSettings::with_seed(3)
+            };
+            let project = generate(&settings);
+            let layers = project.levels[0].layer_instances.as_ref().unwrap();
+            let players: Vec<_> = layers.iter().flat_map(|l| &l.entity_instances).collect();
+            assert_eq!(players.len(), 2);
+            assert_ne!(players[0].px, players[1].px);
+        }
+    }
+}
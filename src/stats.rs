@@ -0,0 +1,392 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::ExternalImpulse;
+use serde::Serialize;
+
+use crate::{
+    board::{DamageEvent, FractureEvent},
+    menu::{AppState, MatchSettings},
+    player::{ChargeSettings, Player, Shot},
+    round::MatchScore,
+    team::{PlayerDamageEvent, Team},
+    turn::TurnStarted,
+    weapon::Weapon,
+};
+
+/// What a gorilla did over the whole match.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlayerStats {
+    pub player: String,
+    pub team: String,
+    pub shots: u32,
+    /// Shots that hit an opponent
+    pub hits: u32,
+    pub accuracy: f32,
+    /// Damage dealt to opponents
+    pub player_damage: f32,
+    pub brick_damage: f32,
+    pub bricks_destroyed: u32,
+    /// Force of the throws, as a fraction of the maximum
+    pub average_charge: f32,
+    pub turns: u32,
+    /// Seconds
+    pub average_turn_time: f32,
+    /// Shots and hits of each weapon thrown
+    pub weapons: BTreeMap<Weapon, WeaponUse>,
+    #[serde(skip)]
+    total_charge: f32,
+    #[serde(skip)]
+    total_turn_time: f32,
+}
+
+/// Shots of one weapon.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WeaponUse {
+    pub shots: u32,
+    /// Shots that hit an opponent
+    pub hits: u32,
+}
+
+impl PlayerStats {
+    /// Fills in the averages.
+    fn finish(&mut self) {
+        let ratio = |total: f32, count: u32| if count > 0 { total / count as f32 } else { 0.0 };
+        self.accuracy = ratio(self.hits as f32, self.shots);
+        self.average_charge = ratio(self.total_charge, self.shots);
+        self.average_turn_time = ratio(self.total_turn_time, self.turns);
+    }
+}
+
+/// Statistics of a finished match.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchReport {
+    pub level: String,
+    pub rounds: u32,
+    /// Winning team of each round, `None` for a draw
+    pub round_winners: Vec<Option<String>>,
+    /// `None` for a draw
+    pub winner: Option<String>,
+    pub players: Vec<PlayerStats>,
+}
+
+impl MatchReport {
+    /// Writes the report as CSV when the path ends in `.csv`, as JSON otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+        let path = path.as_ref();
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        if path.extension().is_some_and(|extension| extension == "csv") {
+            writer.write_all(self.to_csv().as_bytes())?;
+        } else {
+            serde_json::to_writer_pretty(&mut writer, self)?;
+        }
+        writer.flush()
+    }
+
+    /// One row per player. The match fields are repeated on every row, so the files of
+    /// many matches can simply be concatenated.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "level,winner,player,team,shots,hits,accuracy,player_damage,brick_damage,\
+             bricks_destroyed,average_charge,turns,average_turn_time",
+        );
+        for weapon in Weapon::ALL {
+            csv += &format!(",{0}_shots,{0}_hits", weapon.name());
+        }
+        csv += "\n";
+        for stats in &self.players {
+            csv += &format!(
+                "{},{},{},{},{},{},{:.3},{:.1},{:.1},{},{:.3},{},{:.2}",
+                csv_field(&self.level),
+                csv_field(self.winner.as_deref().unwrap_or("")),
+                csv_field(&stats.player),
+                csv_field(&stats.team),
+                stats.shots,
+                stats.hits,
+                stats.accuracy,
+                stats.player_damage,
+                stats.brick_damage,
+                stats.bricks_destroyed,
+                stats.average_charge,
+                stats.turns,
+                stats.average_turn_time,
+            );
+            for weapon in Weapon::ALL {
+                let used = stats.weapons.get(&weapon).cloned().unwrap_or_default();
+                csv += &format!(",{},{}", used.shots, used.hits);
+            }
+            csv += "\n";
+        }
+        csv
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Statistics of the match being played, kept by player name like the scores.
+#[derive(Resource, Debug, Default)]
+struct MatchStats {
+    players: HashMap<String, PlayerStats>,
+    /// Player whose turn it is, and when it started
+    turn: Option<(String, f32)>,
+    /// Player who last damaged each brick, to credit its destruction
+    last_hit: HashMap<Entity, String>,
+    /// Shots that have hit an opponent, so each is counted once
+    hit_shots: HashSet<Entity>,
+    /// Weapon of each shot thrown this round, shots are gone by the time they are counted
+    /// as hits
+    shot_weapons: HashMap<Entity, Weapon>,
+}
+
+impl MatchStats {
+    fn player(&mut self, name: &str, team: &Team) -> &mut PlayerStats {
+        let stats = self
+            .players
+            .entry(name.to_string())
+            .or_insert_with(|| PlayerStats {
+                player: name.to_string(),
+                ..default()
+            });
+        stats.team = team.0.clone();
+        stats
+    }
+
+    fn end_turn(&mut self, now: f32) {
+        if let Some((name, start)) = self.turn.take() {
+            if let Some(stats) = self.players.get_mut(&name) {
+                stats.turns += 1;
+                stats.total_turn_time += now - start;
+            }
+        }
+    }
+
+    fn report(&self, settings: &MatchSettings, score: &MatchScore) -> MatchReport {
+        let mut players: Vec<PlayerStats> = self.players.values().cloned().collect();
+        players.sort_by(|a, b| a.player.cmp(&b.player));
+        for stats in &mut players {
+            stats.finish();
+        }
+        MatchReport {
+            level: settings.level.name.clone(),
+            rounds: score.round,
+            round_winners: score
+                .winners
+                .iter()
+                .map(|winner| winner.as_ref().map(|team| team.0.clone()))
+                .collect(),
+            winner: score.winner().map(|team| team.0),
+            players,
+        }
+    }
+}
+
+/// Where the statistics are written when the match is over.
+#[derive(Resource, Debug)]
+struct StatsFile(PathBuf);
+
+/// Collects shots, hits, damage and turn times of every player, and writes them to a JSON
+/// or CSV file when the match is over.
+pub struct StatsPlugin {
+    pub path: Option<PathBuf>,
+}
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        app.insert_resource(StatsFile(path.clone()))
+            .init_resource::<MatchStats>()
+            .add_systems(OnExit(AppState::Menu), reset_stats)
+            .add_systems(
+                Update,
+                (
+                    add_players,
+                    count_shots,
+                    count_player_damage,
+                    count_bricks,
+                    time_turns,
+                )
+                    .run_if(in_state(AppState::Playing)),
+            )
+            .add_systems(OnExit(AppState::Playing), end_round)
+            .add_systems(OnEnter(AppState::RoundOver), write_stats);
+    }
+}
+
+fn reset_stats(mut stats: ResMut<MatchStats>) {
+    *stats = MatchStats::default();
+}
+
+/// Lists every gorilla, even those knocked out before their first turn.
+fn add_players(
+    mut stats: ResMut<MatchStats>,
+    player_query: Query<(&Player, &Team), Added<Player>>,
+) {
+    for (player, team) in player_query.iter() {
+        stats.player(player.name(), team);
+    }
+}
+
+fn count_shots(
+    charge: Res<ChargeSettings>,
+    mut stats: ResMut<MatchStats>,
+    shot_query: Query<(Entity, &Shot, &ExternalImpulse), Added<Shot>>,
+    player_query: Query<(&Player, &Team)>,
+) {
+    for (entity, shot, impulse) in shot_query.iter() {
+        let Ok((player, team)) = player_query.get(shot.shooter) else {
+            continue;
+        };
+        stats.shot_weapons.insert(entity, shot.weapon);
+        let stats = stats.player(player.name(), team);
+        stats.shots += 1;
+        stats.total_charge += impulse.impulse.length() / charge.max_force;
+        stats.weapons.entry(shot.weapon).or_default().shots += 1;
+    }
+}
+
+fn count_player_damage(
+    mut stats: ResMut<MatchStats>,
+    mut damage_reader: EventReader<PlayerDamageEvent>,
+    player_query: Query<(&Player, &Team)>,
+) {
+    for event in damage_reader.read() {
+        let Some(shooter) = event.shooter else {
+            continue;
+        };
+        let (Ok((_, victim_team)), Ok((shooter, shooter_team))) =
+            (player_query.get(event.player), player_query.get(shooter))
+        else {
+            continue;
+        };
+        if victim_team == shooter_team {
+            continue;
+        }
+        let first_hit = event
+            .shot
+            .filter(|shot| stats.hit_shots.insert(*shot))
+            .map(|shot| stats.shot_weapons.get(&shot).copied());
+        let stats = stats.player(shooter.name(), shooter_team);
+        if let Some(weapon) = first_hit {
+            stats.hits += 1;
+            if let Some(weapon) = weapon {
+                stats.weapons.entry(weapon).or_default().hits += 1;
+            }
+        }
+        stats.player_damage += event.damage;
+    }
+}
+
+fn count_bricks(
+    mut stats: ResMut<MatchStats>,
+    mut damage_reader: EventReader<DamageEvent>,
+    mut fracture_reader: EventReader<FractureEvent>,
+    player_query: Query<(&Player, &Team)>,
+) {
+    for event in damage_reader.read() {
+        let Some(Ok((player, team))) = event.shooter.map(|shooter| player_query.get(shooter))
+        else {
+            continue;
+        };
+        stats.player(player.name(), team).brick_damage += event.damage;
+        stats
+            .last_hit
+            .insert(event.entity, player.name().to_string());
+    }
+    for FractureEvent(brick) in fracture_reader.read() {
+        let Some(name) = stats.last_hit.remove(brick) else {
+            continue;
+        };
+        if let Some(player) = stats.players.get_mut(&name) {
+            player.bricks_destroyed += 1;
+        }
+    }
+}
+
+fn time_turns(
+    time: Res<Time>,
+    mut stats: ResMut<MatchStats>,
+    mut turn_started: EventReader<TurnStarted>,
+    player_query: Query<(&Player, &Team)>,
+) {
+    for event in turn_started.read() {
+        let now = time.elapsed_seconds();
+        stats.end_turn(now);
+        if let Ok((player, team)) = player_query.get(event.player) {
+            stats.player(player.name(), team);
+            stats.turn = Some((player.name().to_string(), now));
+        }
+    }
+}
+
+fn end_round(time: Res<Time>, mut stats: ResMut<MatchStats>) {
+    stats.end_turn(time.elapsed_seconds());
+    stats.last_hit.clear();
+    stats.hit_shots.clear();
+    stats.shot_weapons.clear();
+}
+
+fn write_stats(
+    file: Res<StatsFile>,
+    stats: Res<MatchStats>,
+    settings: Res<MatchSettings>,
+    score: Res<MatchScore>,
+) {
+    if score.round < settings.rounds {
+        return;
+    }
+    let report = stats.report(&settings, &score);
+    match report.save(&file.0) {
+        Ok(()) => info!(path = %file.0.display(), "Wrote match statistics"),
+        Err(err) => error!(path = %file.0.display(), %err, "Failed to write match statistics"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_and_csv() {
+        let mut stats = PlayerStats {
+            player: "Player, the first".to_string(),
+            team: "A".to_string(),
+            shots: 4,
+            hits: 1,
+            turns: 2,
+            total_charge: 2.0,
+            total_turn_time: 9.0,
+            weapons: BTreeMap::from([(Weapon::Bunch, WeaponUse { shots: 1, hits: 1 })]),
+            ..default()
+        };
+        stats.finish();
+        assert_eq!(stats.accuracy, 0.25);
+        assert_eq!(stats.average_charge, 0.5);
+        assert_eq!(stats.average_turn_time, 4.5);
+
+        let report = MatchReport {
+            level: "test.ldtk".to_string(),
+            rounds: 1,
+            round_winners: vec![Some("A".to_string())],
+            winner: Some("A".to_string()),
+            players: vec![stats],
+        };
+        let csv = report.to_csv();
+        let row = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            row,
+            "test.ldtk,A,\"Player, the first\",A,4,1,0.250,0.0,0.0,0,0.500,2,4.50,0,0,1,1"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_rapier2d::pipeline::CollisionEvent;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{env_value, Config},
+    menu::AppState,
+    player::{Player, Shot},
+};
+
+/// The side a gorilla plays for, read from the `Team` field of the LDtk entity. Gorillas
+/// without one play on their own.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Team(pub String);
+
+/// Health left of a gorilla.
+#[derive(Component, Debug)]
+pub struct Health(pub f32);
+
+/// Whether shots hurt the shooter's own team, the shooter included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FriendlyFire {
+    Off,
+    On,
+    /// Teammates take half damage
+    Reduced,
+}
+
+impl FriendlyFire {
+    fn scale(self) -> f32 {
+        match self {
+            FriendlyFire::Off => 0.0,
+            FriendlyFire::On => 1.0,
+            FriendlyFire::Reduced => 0.5,
+        }
+    }
+}
+
+impl std::str::FromStr for FriendlyFire {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(FriendlyFire::Off),
+            "on" => Ok(FriendlyFire::On),
+            "reduced" => Ok(FriendlyFire::Reduced),
+            _ => Err(format!("Unknown friendly fire option: {}", s)),
+        }
+    }
+}
+
+/// How a round is won.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Victory {
+    /// The last team with a gorilla standing wins
+    #[serde(rename = "last-standing")]
+    LastTeamStanding,
+    /// The first team to knock out an opponent wins, like the original game
+    #[serde(rename = "first-knockout")]
+    FirstKnockout,
+}
+
+impl std::str::FromStr for Victory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "last-standing" => Ok(Victory::LastTeamStanding),
+            "first-knockout" => Ok(Victory::FirstKnockout),
+            _ => Err(format!("Unknown victory condition: {}", s)),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TeamSettings {
+    pub friendly_fire: FriendlyFire,
+    pub victory: Victory,
+    pub player_health: f32,
+    /// Damage of a shot hitting a gorilla
+    pub hit_damage: f32,
+}
+
+impl Default for TeamSettings {
+    fn default() -> Self {
+        Self {
+            friendly_fire: FriendlyFire::Off,
+            victory: Victory::LastTeamStanding,
+            player_health: 100.0,
+            hit_damage: 100.0,
+        }
+    }
+}
+
+impl TeamSettings {
+    /// Overridden by `GORILLAS_FRIENDLY_FIRE` (`off`, `on` or `reduced`) and
+    /// `GORILLAS_VICTORY` (`last-standing` or `first-knockout`).
+    pub fn apply_env(&mut self) {
+        if let Some(friendly_fire) = env_value("GORILLAS_FRIENDLY_FIRE") {
+            self.friendly_fire = friendly_fire;
+        }
+        if let Some(victory) = env_value("GORILLAS_VICTORY") {
+            self.victory = victory;
+        }
+    }
+}
+
+/// Damage dealt to a gorilla, by a shot or otherwise.
+#[derive(Event, Debug)]
+pub struct PlayerDamageEvent {
+    pub player: Entity,
+    pub damage: f32,
+    /// Player whose shot did it
+    pub shooter: Option<Entity>,
+    /// The shot that did it
+    pub shot: Option<Entity>,
+}
+
+#[derive(Event, Debug)]
+pub struct PlayerEliminated {
+    pub player: Entity,
+    pub team: Team,
+    /// Player whose shot did it
+    pub shooter: Option<Entity>,
+}
+
+/// Sent when a team has won the round, `winner` is `None` for a draw.
+#[derive(Event, Debug)]
+pub struct RoundOver {
+    pub winner: Option<Team>,
+}
+
+pub struct TeamPlugin;
+
+impl Plugin for TeamPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = app
+            .world
+            .get_resource::<Config>()
+            .map(|config| config.team.clone())
+            .unwrap_or_default();
+        app.insert_resource(settings)
+            .add_event::<PlayerDamageEvent>()
+            .add_event::<PlayerEliminated>()
+            .add_event::<RoundOver>()
+            .add_systems(
+                Update,
+                (
+                    read_player_hits,
+                    damage_players,
+                    check_victory,
+                    announce_winner,
+                )
+                    .chain(),
+            )
+            .add_systems(OnExit(AppState::Playing), clear_winner);
+    }
+}
+
+fn read_player_hits(
+    mut commands: Commands,
+    mut reader: EventReader<CollisionEvent>,
+    settings: Res<TeamSettings>,
+    shot_query: Query<&Shot>,
+    team_query: Query<&Team>,
+    player_query: Query<&Team, With<Player>>,
+    mut damage_writer: EventWriter<PlayerDamageEvent>,
+) {
+    for event in reader.read() {
+        let CollisionEvent::Started(collider1, collider2, _) = event else {
+            continue;
+        };
+        for (shot_entity, other) in [(*collider1, *collider2), (*collider2, *collider1)] {
+            let (Ok(shot), Ok(team)) = (shot_query.get(shot_entity), player_query.get(other))
+            else {
+                continue;
+            };
+            commands.entity(shot_entity).despawn_recursive();
+
+            let friendly = team_query
+                .get(shot.shooter)
+                .is_ok_and(|shooter| shooter == team);
+            let damage = if friendly {
+                settings.hit_damage * settings.friendly_fire.scale()
+            } else {
+                settings.hit_damage
+            };
+            damage_writer.send(PlayerDamageEvent {
+                player: other,
+                damage,
+                shooter: Some(shot.shooter),
+                shot: Some(shot_entity),
+            });
+        }
+    }
+}
+
+fn damage_players(
+    mut commands: Commands,
+    mut damage_reader: EventReader<PlayerDamageEvent>,
+    mut player_query: Query<(&mut Health, &Team, &Player)>,
+    mut eliminated_writer: EventWriter<PlayerEliminated>,
+) {
+    for event in damage_reader.read() {
+        let Ok((mut health, team, player)) = player_query.get_mut(event.player) else {
+            continue;
+        };
+        if health.0 <= 0.0 || event.damage <= 0.0 {
+            continue;
+        }
+        health.0 -= event.damage;
+        info!(
+            player = player.name(),
+            damage = event.damage,
+            health = health.0,
+            "Took damage"
+        );
+
+        if health.0 <= 0.0 {
+            info!(player = player.name(), team = team.0, "Knocked out");
+            commands.entity(event.player).despawn_recursive();
+            eliminated_writer.send(PlayerEliminated {
+                player: event.player,
+                team: team.clone(),
+                shooter: event.shooter,
+            });
+        }
+    }
+}
+
+fn check_victory(
+    settings: Res<TeamSettings>,
+    mut eliminated_reader: EventReader<PlayerEliminated>,
+    player_query: Query<(Entity, &Team), With<Player>>,
+    mut round_over_writer: EventWriter<RoundOver>,
+) {
+    let eliminated: Vec<&PlayerEliminated> = eliminated_reader.read().collect();
+    if eliminated.is_empty() {
+        return;
+    }
+
+    if settings.victory == Victory::FirstKnockout {
+        let knockout = eliminated.iter().find_map(|event| {
+            let (_, shooter_team) = player_query.get(event.shooter?).ok()?;
+            (*shooter_team != event.team).then_some(shooter_team)
+        });
+        if let Some(team) = knockout {
+            round_over_writer.send(RoundOver {
+                winner: Some(team.clone()),
+            });
+            return;
+        }
+    }
+
+    // Eliminated players are despawned at the end of the frame
+    let standing: HashSet<&Team> = player_query
+        .iter()
+        .filter(|(entity, _)| !eliminated.iter().any(|event| event.player == *entity))
+        .map(|(_, team)| team)
+        .collect();
+    if standing.len() <= 1 {
+        round_over_writer.send(RoundOver {
+            winner: standing.into_iter().next().cloned(),
+        });
+    }
+}
+
+#[derive(Component)]
+struct WinnerText;
+
+fn announce_winner(mut commands: Commands, mut round_over_reader: EventReader<RoundOver>) {
+    for event in round_over_reader.read() {
+        let text = match &event.winner {
+            Some(team) => format!("{} wins!", team.0),
+            None => "Draw!".to_string(),
+        };
+        info!(winner = ?event.winner, "{}", text);
+        commands.spawn((
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: 48.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(40.0),
+                left: Val::Percent(40.0),
+                ..default()
+            }),
+            WinnerText,
+        ));
+    }
+}
+
+fn clear_winner(mut commands: Commands, query: Query<Entity, With<WinnerText>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{env_value, Config},
+    menu::AppState,
+    player::Player,
+    team::Team,
+    weapon::Wind,
+};
+
+/// What happens when a player runs out of time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TurnExpiry {
+    /// The turn passes to the next player without a shot
+    #[serde(rename = "end")]
+    EndTurn,
+    /// The shot is fired with whatever charge the gun has
+    #[serde(rename = "fire")]
+    AutoFire,
+}
+
+impl std::str::FromStr for TurnExpiry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "end" => Ok(TurnExpiry::EndTurn),
+            "fire" => Ok(TurnExpiry::AutoFire),
+            _ => Err(format!("Unknown turn expiry: {}", s)),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnSettings {
+    /// Seconds a player has to take their shot
+    pub time_limit: f32,
+    pub on_expiry: TurnExpiry,
+}
+
+impl Default for TurnSettings {
+    fn default() -> Self {
+        Self {
+            time_limit: 20.0,
+            on_expiry: TurnExpiry::AutoFire,
+        }
+    }
+}
+
+/// Players in the order they take their turns. Teams take turns, and each team rotates
+/// through its players that are still standing.
+#[derive(Resource, Debug, Default)]
+pub struct TurnOrder {
+    teams: Vec<(Team, Vec<Entity>)>,
+    /// Index of the player of each team to go next
+    next_member: Vec<usize>,
+    current_team: Option<usize>,
+    active: Option<Entity>,
+}
+
+impl TurnOrder {
+    pub fn add(&mut self, team: Team, player: Entity) {
+        match self.teams.iter_mut().find(|(other, _)| *other == team) {
+            Some((_, members)) => members.push(player),
+            None => {
+                self.teams.push((team, vec![player]));
+                self.next_member.push(0);
+            }
+        }
+    }
+
+    /// The player whose turn it is.
+    pub fn active(&self) -> Option<Entity> {
+        self.active
+    }
+
+    /// All players, team by team.
+    pub fn players(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.teams
+            .iter()
+            .flat_map(|(_, members)| members.iter().copied())
+    }
+
+    /// Moves on to the next team with a player left, and to that team's next player.
+    fn advance(&mut self, alive: impl Fn(Entity) -> bool) -> Option<Entity> {
+        let team_count = self.teams.len();
+        let first = self.current_team.map_or(0, |team| team + 1);
+        for offset in 0..team_count {
+            let team = (first + offset) % team_count;
+            let members = &self.teams[team].1;
+            let start = self.next_member[team];
+            let Some(member) = (0..members.len())
+                .map(|i| (start + i) % members.len())
+                .find(|i| alive(members[*i]))
+            else {
+                continue;
+            };
+            self.next_member[team] = (member + 1) % members.len();
+            self.current_team = Some(team);
+            self.active = Some(members[member]);
+            return self.active;
+        }
+        None
+    }
+}
+
+/// Marks the player whose turn it is.
+#[derive(Component, Debug)]
+pub struct ActivePlayer;
+
+#[derive(Event, Debug)]
+pub struct TurnStarted {
+    pub player: Entity,
+}
+
+/// Sent by the active player to hand the turn over to the next one.
+#[derive(Event, Debug)]
+pub struct TurnEnded {
+    pub player: Entity,
+}
+
+/// Time left in the current turn.
+#[derive(Resource, Debug)]
+pub struct TurnClock {
+    timer: Timer,
+}
+
+impl TurnSettings {
+    /// Overridden by `GORILLAS_TURN_TIME` and `GORILLAS_TURN_EXPIRY` (`end` or `fire`).
+    pub fn apply_env(&mut self) {
+        if let Some(time_limit) = env_value("GORILLAS_TURN_TIME") {
+            self.time_limit = time_limit;
+        }
+        if let Some(on_expiry) = env_value("GORILLAS_TURN_EXPIRY") {
+            self.on_expiry = on_expiry;
+        }
+    }
+}
+
+impl TurnClock {
+    pub fn restart(&mut self, settings: &TurnSettings) {
+        self.timer = Timer::from_seconds(settings.time_limit, TimerMode::Once);
+    }
+
+    pub fn expired(&self) -> bool {
+        self.timer.finished()
+    }
+
+    pub fn remaining_secs(&self) -> f32 {
+        self.timer.remaining_secs()
+    }
+}
+
+pub struct TurnPlugin;
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = app
+            .world
+            .get_resource::<Config>()
+            .map(|config| config.turn.clone())
+            .unwrap_or_default();
+        app.insert_resource(TurnClock {
+            timer: Timer::from_seconds(settings.time_limit, TimerMode::Once),
+        })
+        .insert_resource(settings)
+        .init_resource::<TurnOrder>()
+        .add_event::<TurnStarted>()
+        .add_event::<TurnEnded>()
+        .add_systems(Startup, spawn_turn_hud)
+        .add_systems(Update, (tick_turn_clock, update_turn_hud))
+        .add_systems(OnExit(AppState::Playing), reset_turns)
+        .add_systems(PostUpdate, advance_turn);
+    }
+}
+
+fn tick_turn_clock(time: Res<Time>, order: Res<TurnOrder>, mut clock: ResMut<TurnClock>) {
+    if order.active().is_some() {
+        clock.timer.tick(time.delta());
+    }
+}
+
+/// Forgets the players of the round that ended.
+fn reset_turns(mut order: ResMut<TurnOrder>) {
+    *order = TurnOrder::default();
+}
+
+/// Moves [`ActivePlayer`] to the next player when the turn has ended, or to the first one
+/// when the players have just been spawned.
+fn advance_turn(
+    mut commands: Commands,
+    mut order: ResMut<TurnOrder>,
+    settings: Res<TurnSettings>,
+    mut clock: ResMut<TurnClock>,
+    mut ended_events: EventReader<TurnEnded>,
+    mut started_events: EventWriter<TurnStarted>,
+    q_player: Query<(), With<Player>>,
+) {
+    // Stale events from a player whose turn is already over are ignored
+    let active = order.active();
+    let ended = ended_events
+        .read()
+        .filter(|event| Some(event.player) == active)
+        .count()
+        > 0;
+    // The turn also passes on when the active player is knocked out
+    if active.is_some_and(|player| q_player.contains(player)) && !ended {
+        return;
+    }
+    let Some(player) = order.advance(|player| q_player.contains(player)) else {
+        return;
+    };
+
+    if let Some(mut entity) = active.and_then(|player| commands.get_entity(player)) {
+        entity.remove::<ActivePlayer>();
+    }
+    commands.entity(player).insert(ActivePlayer);
+    clock.restart(&settings);
+    started_events.send(TurnStarted { player });
+}
+
+#[derive(Component)]
+struct TurnHud;
+
+fn spawn_turn_hud(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                TurnHud,
+            ));
+        });
+}
+
+fn update_turn_hud(
+    clock: Res<TurnClock>,
+    wind: Res<Wind>,
+    q_player: Query<&Player, With<ActivePlayer>>,
+    mut hud_query: Query<&mut Text, With<TurnHud>>,
+) {
+    let label = match q_player.get_single() {
+        Ok(player) => format!(
+            "{}  {:.0}{}",
+            player.name(),
+            clock.remaining_secs().ceil(),
+            wind_label(wind.0)
+        ),
+        Err(_) => String::new(),
+    };
+    for mut text in hud_query.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
+
+/// An arrow pointing where the wind blows, longer the stronger it is.
+fn wind_label(wind: f32) -> String {
+    let length = (wind.abs() / 10.0).ceil() as usize;
+    match wind {
+        wind if wind > 0.0 => format!("  wind {}>", "-".repeat(length)),
+        wind if wind < 0.0 => format!("  wind <{}", "-".repeat(length)),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn teams_take_turns() {
+        let mut order = TurnOrder::default();
+        assert_eq!(order.advance(|_| true), None);
+
+        let [a1, a2, b1] = [1, 2, 3].map(Entity::from_raw);
+        order.add(Team("A".to_string()), a1);
+        order.add(Team("A".to_string()), a2);
+        order.add(Team("B".to_string()), b1);
+        let turns: Vec<_> = (0..5).map(|_| order.advance(|_| true).unwrap()).collect();
+        assert_eq!(turns, vec![a1, b1, a2, b1, a1]);
+
+        // Knocked out players are skipped
+        assert_eq!(order.advance(|player| player != b1), Some(a2));
+        assert_eq!(order.advance(|player| player != b1), Some(a1));
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier2d::{
+    dynamics::{ExternalImpulse, RigidBody, Velocity},
+    geometry::{ActiveEvents, Collider},
+    pipeline::CollisionEvent,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::env_value,
+    menu::{AppState, MatchSettings},
+    player::Shot,
+};
+
+/// What a player throws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Weapon {
+    /// The classic banana, it never runs out
+    Banana,
+    /// A whole bunch of bananas, bigger and heavier
+    Bunch,
+}
+
+impl Weapon {
+    pub const ALL: [Weapon; 2] = [Weapon::Banana, Weapon::Bunch];
+
+    /// Name in config, replay and statistics files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Weapon::Banana => "banana",
+            Weapon::Bunch => "bunch",
+        }
+    }
+
+    /// Shots each player starts with, `None` for unlimited.
+    pub fn starting_ammo(self) -> Option<u32> {
+        match self {
+            Weapon::Banana => None,
+            Weapon::Bunch => Some(2),
+        }
+    }
+}
+
+/// Collider of a shot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShotShape {
+    Ball,
+    /// Lying along the x axis, half as thick as it is long
+    Capsule,
+    Cuboid,
+    /// Convex hull around the opaque pixels of the sprite, a ball until the sprite is loaded
+    SpriteHull,
+}
+
+impl std::str::FromStr for ShotShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ball" => Ok(ShotShape::Ball),
+            "capsule" => Ok(ShotShape::Capsule),
+            "cuboid" => Ok(ShotShape::Cuboid),
+            "sprite" => Ok(ShotShape::SpriteHull),
+            _ => Err(format!("Unknown shot shape: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WeaponStats {
+    /// Side of the shot in pixels
+    pub size: f32,
+    pub shape: ShotShape,
+    /// Image drawn on the shot, tinted by `color`
+    pub sprite: Option<String>,
+    pub color: Color,
+    /// Radians per second the shot spins as it leaves the hand
+    pub spin: f32,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct WeaponSettings {
+    pub weapons: HashMap<Weapon, WeaponStats>,
+    /// Spinning shots kick off sideways when they bounce
+    pub bounce_spin: bool,
+    /// Fraction of the spin turned into sideways speed on each bounce
+    pub spin_grip: f32,
+    /// Strongest wind when the match has wind, in pixels per second squared
+    pub max_wind: f32,
+}
+
+impl Default for WeaponSettings {
+    fn default() -> Self {
+        let weapons = [
+            (
+                Weapon::Banana,
+                WeaponStats {
+                    size: 8.0,
+                    shape: ShotShape::SpriteHull,
+                    sprite: Some("banana.png".to_string()),
+                    color: Color::WHITE,
+                    spin: 10.0,
+                },
+            ),
+            (
+                Weapon::Bunch,
+                WeaponStats {
+                    size: 14.0,
+                    shape: ShotShape::Ball,
+                    sprite: Some("banana.png".to_string()),
+                    color: Color::rgb(1.0, 0.85, 0.5),
+                    spin: 4.0,
+                },
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        Self {
+            weapons,
+            bounce_spin: true,
+            spin_grip: 0.3,
+            max_wind: 30.0,
+        }
+    }
+}
+
+impl WeaponSettings {
+    /// Defaults with the shape of every weapon overridden by `GORILLAS_SHOT_SHAPE` (`ball`,
+    /// `capsule`, `cuboid` or `sprite`).
+    pub fn from_env() -> Self {
+        let mut settings = Self::default();
+        if let Some(shape) = env_value::<ShotShape>("GORILLAS_SHOT_SHAPE") {
+            for stats in settings.weapons.values_mut() {
+                stats.shape = shape;
+            }
+        }
+        settings
+    }
+
+    pub fn get(&self, weapon: Weapon) -> &WeaponStats {
+        &self.weapons[&weapon]
+    }
+}
+
+/// Sideways push on shots in flight, in pixels per second squared.
+#[derive(Resource, Debug, Default)]
+pub struct Wind(pub f32);
+
+/// Loaded sprites of the weapons.
+#[derive(Resource, Debug, Default)]
+struct WeaponSprites(HashMap<Weapon, Handle<Image>>);
+
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WeaponSettings::from_env())
+            .init_resource::<WeaponSprites>()
+            .init_resource::<Wind>()
+            .add_systems(Startup, load_weapon_sprites)
+            .add_systems(OnEnter(AppState::Playing), pick_wind)
+            .add_systems(Update, (spin_on_bounce, blow_wind));
+    }
+}
+
+fn load_weapon_sprites(
+    asset_server: Res<AssetServer>,
+    settings: Res<WeaponSettings>,
+    mut sprites: ResMut<WeaponSprites>,
+) {
+    for (weapon, stats) in &settings.weapons {
+        if let Some(path) = &stats.sprite {
+            sprites.0.insert(*weapon, asset_server.load(path.clone()));
+        }
+    }
+}
+
+/// Picks a random wind for the match, or none when it is played without.
+fn pick_wind(
+    settings: Res<WeaponSettings>,
+    match_settings: Res<MatchSettings>,
+    mut wind: ResMut<Wind>,
+) {
+    wind.0 = if match_settings.wind {
+        rand::thread_rng().gen_range(-settings.max_wind..=settings.max_wind)
+    } else {
+        0.0
+    };
+}
+
+fn blow_wind(time: Res<Time>, wind: Res<Wind>, mut shot_query: Query<&mut Velocity, With<Shot>>) {
+    if wind.0 == 0.0 {
+        return;
+    }
+    for mut velocity in shot_query.iter_mut() {
+        velocity.linvel.x += wind.0 * time.delta_seconds();
+    }
+}
+
+/// Everything needed to throw a shot.
+#[derive(SystemParam)]
+pub struct ShotSpawner<'w> {
+    settings: Res<'w, WeaponSettings>,
+    sprites: Res<'w, WeaponSprites>,
+    images: Res<'w, Assets<Image>>,
+}
+
+impl ShotSpawner<'_> {
+    pub fn stats(&self, weapon: Weapon) -> &WeaponStats {
+        self.settings.get(weapon)
+    }
+
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        shooter: Entity,
+        position: Vec2,
+        impulse: Vec2,
+        weapon: Weapon,
+    ) -> Entity {
+        let stats = self.stats(weapon);
+        let sprite = self.sprites.0.get(&weapon);
+        let image = sprite.and_then(|handle| self.images.get(handle));
+        let collider = match stats.shape {
+            ShotShape::Ball => Collider::ball(stats.size / 2.0),
+            ShotShape::Capsule => Collider::capsule_x(stats.size / 4.0, stats.size / 4.0),
+            ShotShape::Cuboid => Collider::cuboid(stats.size / 2.0, stats.size / 2.0),
+            ShotShape::SpriteHull => image
+                .and_then(|image| sprite_hull(image, stats.size))
+                .and_then(|points| Collider::convex_hull(&points))
+                .unwrap_or_else(|| Collider::ball(stats.size / 2.0)),
+        };
+        // Bananas spin forwards, clockwise when thrown to the right
+        let spin = -impulse.x.signum() * stats.spin;
+
+        commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::splat(stats.size)),
+                        color: stats.color,
+                        ..Default::default()
+                    },
+                    texture: sprite.cloned().unwrap_or_default(),
+                    transform: Transform::from_xyz(position.x, position.y, 0.0),
+                    ..Default::default()
+                },
+                Shot { shooter, weapon },
+            ))
+            .insert(RigidBody::Dynamic)
+            .insert(collider)
+            .insert(Velocity::angular(spin))
+            .insert(ExternalImpulse {
+                impulse,
+                ..Default::default()
+            })
+            .insert(ActiveEvents::COLLISION_EVENTS)
+            .id()
+    }
+}
+
+/// Points around the opaque pixels of an RGBA image drawn `size` pixels wide, centered on
+/// the origin.
+fn sprite_hull(image: &Image, size: f32) -> Option<Vec<Vec2>> {
+    let width = image.texture_descriptor.size.width as usize;
+    let height = image.texture_descriptor.size.height as usize;
+    if width == 0 || height == 0 || image.data.len() < width * height * 4 {
+        return None;
+    }
+
+    let scale = Vec2::new(size / width as f32, size / height as f32);
+    let mut points = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if image.data[(y * width + x) * 4 + 3] < 128 {
+                continue;
+            }
+            // Corners of the pixel, with y pointing up
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let corner = Vec2::new(
+                    (x + dx) as f32 - width as f32 / 2.0,
+                    height as f32 / 2.0 - (y + dy) as f32,
+                );
+                points.push(corner * scale);
+            }
+        }
+    }
+    (!points.is_empty()).then_some(points)
+}
+
+/// Turns part of a shot's spin into sideways speed when it bounces, as a spinning ball
+/// grips the ground.
+fn spin_on_bounce(
+    settings: Res<WeaponSettings>,
+    mut reader: EventReader<CollisionEvent>,
+    mut shot_query: Query<(&Shot, &mut Velocity)>,
+) {
+    if !settings.bounce_spin {
+        reader.clear();
+        return;
+    }
+    for event in reader.read() {
+        let CollisionEvent::Started(collider1, collider2, _) = event else {
+            continue;
+        };
+        for entity in [*collider1, *collider2] {
+            if let Ok((shot, mut velocity)) = shot_query.get_mut(entity) {
+                let radius = settings.get(shot.weapon).size / 2.0;
+                let grip = settings.spin_grip;
+                velocity.linvel.x -= velocity.angvel * radius * grip;
+                velocity.angvel *= 1.0 - grip;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+    use super::*;
+
+    #[test]
+    fn hull_covers_opaque_pixels() {
+        // A 2x2 image with only the bottom right pixel opaque
+        let mut data = vec![0; 16];
+        data[15] = 255;
+        let image = Image::new(
+            Extent3d {
+                width: 2,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+
+        let points = sprite_hull(&image, 8.0).unwrap();
+        let min = points.iter().fold(Vec2::MAX, |min, point| min.min(*point));
+        let max = points.iter().fold(Vec2::MIN, |max, point| max.max(*point));
+        assert_eq!(min, Vec2::new(0.0, -4.0));
+        assert_eq!(max, Vec2::new(4.0, 0.0));
+    }
+}